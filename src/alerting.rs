@@ -0,0 +1,863 @@
+use crate::LogEntry;
+use chrono::{DateTime, Duration, Local, SecondsFormat, Timelike, Utc};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    convert::TryFrom,
+    fmt,
+};
+
+// What a rule counts for every log entry that matches its filters
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Requests,
+    Bytes,
+    // Percentage of requests matching the status filter among all requests in scope
+    Ratio,
+}
+
+// How the per-interval samples of a window are turned into a single value
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    // Average per-second rate over the whole window
+    Avg,
+    // Highest per-second rate of a single refresh interval within the window
+    Max,
+    // Lowest per-second rate of a single refresh interval within the window
+    Min,
+    // Plain total over the window
+    Sum,
+}
+
+// Evaluate the rule separately for every section or every client address
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Section,
+    Ip,
+}
+
+// Turns a rule into anomaly detection: instead of the value itself the rule compares how many
+// standard deviations the value is away from its exponentially weighted moving average
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Anomaly {
+    // Weight of the latest value in the moving average, between 0 and 1
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+    // Keep a separate baseline for every hour of the day to follow diurnal traffic
+    #[serde(default)]
+    pub seasonal: bool,
+    // Amount of observations a baseline needs before it's trusted
+    #[serde(default = "default_warmup")]
+    pub warmup: u64,
+}
+
+fn default_alpha() -> f64 {
+    0.1
+}
+
+fn default_warmup() -> u64 {
+    10
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Comparator {
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+}
+
+impl Comparator {
+    pub fn compare(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparator::Greater => value > threshold,
+            Comparator::GreaterOrEqual => value >= threshold,
+            Comparator::Less => value < threshold,
+            Comparator::LessOrEqual => value <= threshold,
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self {
+            Comparator::Greater => ">",
+            Comparator::GreaterOrEqual => ">=",
+            Comparator::Less => "<",
+            Comparator::LessOrEqual => "<=",
+        };
+        write!(f, "{}", sign)
+    }
+}
+
+// Either an exact HTTP code like "404" or a whole class like "5xx"
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub enum StatusMatcher {
+    Code(u16),
+    Class(u16),
+}
+
+impl StatusMatcher {
+    pub fn matches(self, code: u16) -> bool {
+        match self {
+            StatusMatcher::Code(expected) => code == expected,
+            StatusMatcher::Class(class) => code / 100 == class,
+        }
+    }
+}
+
+impl TryFrom<String> for StatusMatcher {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let lowercase = value.to_lowercase();
+        if let Some(class) = lowercase.strip_suffix("xx") {
+            if let Ok(class @ 1..=5) = class.parse::<u16>() {
+                return Ok(StatusMatcher::Class(class));
+            }
+        } else if let Ok(code) = lowercase.parse::<u16>() {
+            return Ok(StatusMatcher::Code(code));
+        }
+        Err(format!(
+            "Invalid status '{}', expected a code like '404' or a class like '5xx'",
+            value
+        ))
+    }
+}
+
+impl fmt::Display for StatusMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusMatcher::Code(code) => write!(f, "{}", code),
+            StatusMatcher::Class(class) => write!(f, "{}xx", class),
+        }
+    }
+}
+
+// A single named alert rule, as described in the config file:
+//
+// [[alerts]]
+// name = "5xx on /api"
+// metric = "requests"
+// section = "api"
+// status = ["5xx"]
+// aggregation = "avg"
+// window = 120
+// comparator = ">="
+// threshold = 10
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub name: String,
+    #[serde(default = "default_metric")]
+    pub metric: Metric,
+    #[serde(default = "default_aggregation")]
+    pub aggregation: Aggregation,
+    // Length of the window in seconds
+    pub window: u64,
+    #[serde(default = "default_comparator")]
+    pub comparator: Comparator,
+    pub threshold: f64,
+    // Only count requests to this section, e.g. "api" for "/api/users"
+    #[serde(default)]
+    pub section: Option<String>,
+    // Only count requests with these response codes
+    #[serde(default)]
+    pub status: Vec<StatusMatcher>,
+    // Never fire if the window holds fewer requests than this, so low traffic can't trigger it
+    #[serde(default)]
+    pub min_requests: u64,
+    // Fire if any single section or IP satisfies the rule on its own
+    #[serde(default)]
+    pub group_by: Option<GroupBy>,
+    // Compare the deviation from the usual value instead of the value itself
+    #[serde(default)]
+    pub anomaly: Option<Anomaly>,
+}
+
+fn default_metric() -> Metric {
+    Metric::Requests
+}
+
+fn default_aggregation() -> Aggregation {
+    Aggregation::Avg
+}
+
+fn default_comparator() -> Comparator {
+    Comparator::GreaterOrEqual
+}
+
+impl AlertRule {
+    // The rule that used to be the only alert: average hit rate over the alerting interval
+    pub fn total_traffic(window: u64, threshold: u64) -> Self {
+        AlertRule {
+            name: String::from("High traffic"),
+            metric: Metric::Requests,
+            aggregation: Aggregation::Avg,
+            window,
+            comparator: Comparator::GreaterOrEqual,
+            threshold: threshold as f64,
+            section: None,
+            status: vec![],
+            min_requests: 0,
+            group_by: None,
+            anomaly: None,
+        }
+    }
+
+    // Rules that watch the overall hit rate, like the one built from the command line flags
+    pub fn is_total_rate(&self) -> bool {
+        self.metric == Metric::Requests
+            && self.aggregation != Aggregation::Sum
+            && self.section.is_none()
+            && self.status.is_empty()
+            && self.group_by.is_none()
+            && self.anomaly.is_none()
+    }
+
+    fn in_scope(&self, log_entry: &LogEntry) -> bool {
+        match &self.section {
+            Some(section) => section.trim_matches('/') == log_entry.section(),
+            None => true,
+        }
+    }
+
+    fn matches_status(&self, log_entry: &LogEntry) -> bool {
+        self.status.is_empty()
+            || self
+                .status
+                .iter()
+                .any(|status| status.matches(log_entry.response_code))
+    }
+
+    // Key the log entry is accounted under, all entries share the same key without grouping
+    fn key(&self, log_entry: &LogEntry) -> String {
+        match self.group_by {
+            Some(GroupBy::Section) => format!("/{}", log_entry.section()),
+            Some(GroupBy::Ip) => log_entry.ip_address.to_string(),
+            None => String::new(),
+        }
+    }
+
+    // One of UNITS
+    pub fn unit(&self) -> &'static str {
+        if self.anomaly.is_some() {
+            return "σ";
+        }
+        match (self.metric, self.aggregation) {
+            (Metric::Requests, Aggregation::Sum) => "req",
+            (Metric::Requests, _) => "req/s",
+            (Metric::Bytes, Aggregation::Sum) => "B",
+            (Metric::Bytes, _) => "B/s",
+            (Metric::Ratio, _) => "%",
+        }
+    }
+}
+
+// Every unit the value of an alert can be in
+pub const UNITS: [&str; 6] = ["req", "req/s", "B", "B/s", "%", "σ"];
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metric = match self.metric {
+            Metric::Requests => "requests",
+            Metric::Bytes => "bytes",
+            Metric::Ratio => "ratio",
+        };
+        let aggregation = match self.aggregation {
+            Aggregation::Avg => "avg",
+            Aggregation::Max => "max",
+            Aggregation::Min => "min",
+            Aggregation::Sum => "sum",
+        };
+        if self.anomaly.is_some() {
+            write!(f, "deviation of ")?;
+        }
+        write!(f, "{} {}", aggregation, metric)?;
+        match self.group_by {
+            Some(GroupBy::Section) => write!(f, " per section")?,
+            Some(GroupBy::Ip) => write!(f, " per IP")?,
+            None => {}
+        }
+        if let Some(section) = &self.section {
+            write!(f, " /{}", section.trim_matches('/'))?;
+        }
+        if !self.status.is_empty() {
+            let status = self
+                .status
+                .iter()
+                .map(|status| status.to_string())
+                .collect::<Vec<_>>();
+            write!(f, " [{}]", status.join(","))?;
+        }
+        write!(
+            f,
+            " {} {}{} over {}s",
+            self.comparator,
+            self.threshold,
+            self.unit(),
+            self.window
+        )?;
+        if self.min_requests > 0 {
+            write!(f, " with at least {} req", self.min_requests)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AlertState {
+    pub firing: bool,
+    // None until the alert changes its state for the first time
+    pub change_time: Option<DateTime<Utc>>,
+    pub value: f64,
+    // Section or IP the value belongs to for grouped rules, the worst offender while firing
+    pub key: Option<String>,
+}
+
+// A single fire or resolve transition of an alert
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertEvent {
+    pub name: String,
+    pub firing: bool,
+    pub time: DateTime<Utc>,
+    // Value that caused the transition along with what it was compared to
+    pub value: f64,
+    pub threshold: f64,
+    pub unit: &'static str,
+    // Length of the window of the rule in seconds
+    pub window: u64,
+    pub key: Option<String>,
+    // How long the alert stayed in the previous state, None if it never changed before
+    pub duration: Option<Duration>,
+}
+
+// Format duration as e.g. "1h02m03s"
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    if seconds >= 3600 {
+        format!(
+            "{}h{:02}m{:02}s",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    } else if seconds >= 60 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+impl fmt::Display for AlertEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}: {:.2}{} (threshold {}{})",
+            self.time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            if self.firing { "FIRING" } else { "RESOLVED" },
+            self.name,
+            self.value,
+            self.unit,
+            self.threshold,
+            self.unit
+        )?;
+        if let Some(key) = &self.key {
+            write!(f, " by {}", key)?;
+        }
+        match (self.firing, self.duration) {
+            (true, Some(duration)) => write!(f, ", quiet for {}", format_duration(duration)),
+            (false, Some(duration)) => write!(f, ", fired for {}", format_duration(duration)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Snapshot of a rule and its state that is sent to the UI every refresh interval
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertStatus {
+    pub rule: AlertRule,
+    pub state: AlertState,
+}
+
+// What a rule accumulated during a single refresh interval
+#[derive(Clone, Copy, Debug, Default)]
+struct Sample {
+    // Requests or bytes matching the rule
+    value: u64,
+    // All requests in the scope of the rule, regardless of their status
+    requests: u64,
+}
+
+impl Sample {
+    fn ratio(value: u64, requests: u64) -> f64 {
+        if requests == 0 {
+            0.0
+        } else {
+            value as f64 * 100.0 / requests as f64
+        }
+    }
+}
+
+// Moving average and variance of the values a rule observed
+#[derive(Clone, Copy, Debug, Default)]
+struct Baseline {
+    mean: f64,
+    variance: f64,
+    observations: u64,
+}
+
+impl Baseline {
+    // How many standard deviations the value is away from the mean, None while warming up
+    fn score(&self, value: f64, warmup: u64, refresh_interval: u64) -> Option<f64> {
+        if self.observations < warmup.max(1) {
+            return None;
+        }
+        // A flat baseline would turn any tiny change into a huge deviation. Requests arrive at
+        // random, so the deviation is never taken for less than the Poisson noise of the mean
+        // rate, nor for less than a single request per refresh interval
+        let refresh_interval = refresh_interval as f64;
+        let deviation = self
+            .variance
+            .sqrt()
+            .max((self.mean.max(0.0) / refresh_interval).sqrt())
+            .max(1.0 / refresh_interval);
+        Some((value - self.mean) / deviation)
+    }
+
+    fn update(&mut self, value: f64, alpha: f64) {
+        if self.observations == 0 {
+            self.mean = value;
+        } else {
+            let diff = value - self.mean;
+            let increment = alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+        }
+        self.observations += 1;
+    }
+}
+
+// A rule together with everything needed to evaluate it over time
+pub struct Alert {
+    pub rule: AlertRule,
+    pub state: AlertState,
+    // Values accumulated since the last refresh, per section or IP for grouped rules
+    current: HashMap<String, Sample>,
+    // FIFO of values of the last refresh intervals, exclusively relevant for the window
+    samples: VecDeque<HashMap<String, Sample>>,
+    max_samples: usize,
+    // Baselines of anomaly detecting rules per key and per hour of the day if seasonal
+    baselines: HashMap<(String, Option<u32>), Baseline>,
+}
+
+impl Alert {
+    pub fn new(rule: AlertRule, refresh_interval: u64) -> Self {
+        // Window that is not a multiple of the refresh interval is rounded up
+        let max_samples = rule.window.div_ceil(refresh_interval).max(1) as usize;
+        Alert {
+            rule,
+            state: AlertState::default(),
+            current: HashMap::new(),
+            samples: VecDeque::with_capacity(max_samples),
+            max_samples,
+            baselines: HashMap::new(),
+        }
+    }
+
+    // Start over with a changed rule or refresh interval. The state is kept, so a firing alert
+    // still resolves once the new window is observed
+    pub fn reconfigure(&mut self, rule: AlertRule, refresh_interval: u64) {
+        let state = std::mem::take(&mut self.state);
+        *self = Alert {
+            state,
+            ..Alert::new(rule, refresh_interval)
+        };
+    }
+
+    pub fn record(&mut self, log_entry: &LogEntry) {
+        if !self.rule.in_scope(log_entry) {
+            return;
+        }
+        let sample = self.current.entry(self.rule.key(log_entry)).or_default();
+        sample.requests += 1;
+        if self.rule.matches_status(log_entry) {
+            sample.value += match self.rule.metric {
+                Metric::Requests | Metric::Ratio => 1,
+                Metric::Bytes => log_entry.response_size as u64,
+            };
+        }
+    }
+
+    // Turn the samples of a single key over the whole window into the value compared to threshold
+    fn aggregate(&self, window: &[Sample], refresh_interval: f64) -> f64 {
+        let value = window.iter().map(|sample| sample.value).sum::<u64>();
+        let requests = window.iter().map(|sample| sample.requests).sum::<u64>();
+        // Rate of a single refresh interval, used by max and min aggregations
+        let interval_values = window.iter().map(|sample| match self.rule.metric {
+            Metric::Ratio => Sample::ratio(sample.value, sample.requests),
+            _ => sample.value as f64 / refresh_interval,
+        });
+        match (self.rule.metric, self.rule.aggregation) {
+            // Ratio over the whole window, summing up percentages makes no sense
+            (Metric::Ratio, Aggregation::Avg) | (Metric::Ratio, Aggregation::Sum) => {
+                Sample::ratio(value, requests)
+            }
+            (_, Aggregation::Avg) => value as f64 / (window.len() as f64 * refresh_interval),
+            (_, Aggregation::Sum) => value as f64,
+            (_, Aggregation::Max) => interval_values.fold(f64::MIN, f64::max),
+            (_, Aggregation::Min) => interval_values.fold(f64::MAX, f64::min),
+        }
+    }
+
+    // Close the current refresh interval and re-evaluate the rule.
+    // Returns the transition if the alert changed its state
+    pub fn evaluate(&mut self, refresh_interval: u64) -> Option<AlertEvent> {
+        self.evaluate_at(refresh_interval, Utc::now())
+    }
+
+    // Same as evaluate, but the interval is closed at the given time instead of now,
+    // e.g. when replaying a log file
+    pub fn evaluate_at(&mut self, refresh_interval: u64, now: DateTime<Utc>) -> Option<AlertEvent> {
+        if self.rule.group_by.is_none() {
+            // A rule without grouping has a value even if nothing was recorded
+            self.current.entry(String::new()).or_default();
+        }
+        if self.samples.len() == self.max_samples {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(std::mem::take(&mut self.current));
+
+        // Don't judge until the whole window is observed
+        if self.samples.len() < self.max_samples {
+            return None;
+        }
+        let keys = self
+            .samples
+            .iter()
+            .flat_map(|samples| samples.keys())
+            .collect::<HashSet<_>>();
+        let windows = keys
+            .into_iter()
+            .map(|key| {
+                let window = self
+                    .samples
+                    .iter()
+                    .map(|samples| samples.get(key).copied().unwrap_or_default())
+                    .collect::<Vec<_>>();
+                let requests = window.iter().map(|sample| sample.requests).sum::<u64>();
+                let value = self.aggregate(&window, refresh_interval as f64);
+                (key.clone(), requests, Some(value))
+            })
+            .collect::<Vec<_>>();
+        let windows = self.score_anomalies(windows, refresh_interval, now);
+        // Values of every key along with the verdict whether the key alone fires the alert
+        let mut values = windows
+            .into_iter()
+            .map(|(key, requests, value)| match value {
+                Some(value) => {
+                    let firing = requests >= self.rule.min_requests
+                        && self.rule.comparator.compare(value, self.rule.threshold);
+                    (firing, value, key)
+                }
+                // Baseline is still warming up
+                None => (false, 0.0, key),
+            })
+            .collect::<Vec<_>>();
+        // Firing keys first, then the most extreme value in the direction of the comparator
+        let less = matches!(
+            self.rule.comparator,
+            Comparator::Less | Comparator::LessOrEqual
+        );
+        values.sort_by(|a, b| {
+            let by_value = if less {
+                a.1.partial_cmp(&b.1)
+            } else {
+                b.1.partial_cmp(&a.1)
+            };
+            b.0.cmp(&a.0)
+                .then(by_value.unwrap_or(std::cmp::Ordering::Equal))
+        });
+        let (firing, value, key) = match values.into_iter().next() {
+            Some((firing, value, key)) => (firing, value, Some(key)),
+            // A grouped rule that has not seen a single key yet
+            None => (false, 0.0, None),
+        };
+        self.state.value = value;
+        self.state.key = self.rule.group_by.and(key);
+        if firing == self.state.firing {
+            return None;
+        }
+        let event = AlertEvent {
+            name: self.rule.name.clone(),
+            firing,
+            time: now,
+            value: self.state.value,
+            threshold: self.rule.threshold,
+            unit: self.rule.unit(),
+            window: self.rule.window,
+            key: self.state.key.clone(),
+            duration: self.state.change_time.map(|time| now - time),
+        };
+        self.state.firing = firing;
+        self.state.change_time = Some(now);
+        Some(event)
+    }
+
+    // Replace values of anomaly detecting rules with their deviation from the baseline
+    fn score_anomalies(
+        &mut self,
+        windows: Vec<(String, u64, Option<f64>)>,
+        refresh_interval: u64,
+        now: DateTime<Utc>,
+    ) -> Vec<(String, u64, Option<f64>)> {
+        let anomaly = match self.rule.anomaly {
+            Some(anomaly) => anomaly,
+            None => return windows,
+        };
+        let hour = if anomaly.seasonal {
+            Some(now.with_timezone(&Local).hour())
+        } else {
+            None
+        };
+        windows
+            .into_iter()
+            .map(|(key, requests, value)| {
+                let value = value.unwrap_or_default();
+                let baseline = self.baselines.entry((key.clone(), hour)).or_default();
+                let score = baseline.score(value, anomaly.warmup, refresh_interval);
+                baseline.update(value, anomaly.alpha);
+                (key, requests, score)
+            })
+            .collect()
+    }
+
+    pub fn status(&self) -> AlertStatus {
+        AlertStatus {
+            rule: self.rule.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_log_entry;
+
+    fn log(path: &str, code: u16, size: u64) -> LogEntry {
+        log_from("127.0.0.1", path, code, size)
+    }
+
+    fn log_from(ip: &str, path: &str, code: u16, size: u64) -> LogEntry {
+        parse_log_entry(&format!(
+            "{} - - [09/May/2018:16:00:39 +0000] \"GET {} HTTP/1.0\" {} {}",
+            ip, path, code, size
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_status_matcher() {
+        assert_eq!(
+            StatusMatcher::try_from(String::from("404")),
+            Ok(StatusMatcher::Code(404))
+        );
+        assert_eq!(
+            StatusMatcher::try_from(String::from("5XX")),
+            Ok(StatusMatcher::Class(5))
+        );
+        assert!(StatusMatcher::try_from(String::from("9xx")).is_err());
+        assert!(StatusMatcher::try_from(String::from("abc")).is_err());
+        assert!(StatusMatcher::Class(5).matches(503));
+        assert!(!StatusMatcher::Class(5).matches(404));
+    }
+
+    #[test]
+    fn test_alert_fires_and_resolves() {
+        let mut rule = AlertRule::total_traffic(20, 2);
+        rule.section = Some(String::from("/api"));
+        rule.status = vec![StatusMatcher::Class(5)];
+        let mut alert = Alert::new(rule, 10);
+
+        for _ in 0..30 {
+            alert.record(&log("/api/users", 500, 10));
+        }
+        alert.record(&log("/users", 500, 10));
+        alert.record(&log("/api/users", 200, 10));
+        // Window of 20s is not observed yet
+        assert!(alert.evaluate(10).is_none());
+        for _ in 0..10 {
+            alert.record(&log("/api", 502, 10));
+        }
+        // (30 + 10) / 20s >= 2
+        assert!(alert.evaluate(10).is_some());
+        assert!(alert.state.firing);
+        assert_eq!(alert.state.value, 2.0);
+        // (10 + 0) / 20s < 2
+        let event = alert.evaluate(10).unwrap();
+        assert!(!event.firing);
+        assert!(!alert.state.firing);
+        assert_eq!(event.value, 0.5);
+        assert!(event.duration.is_some());
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let mut alert = Alert::new(AlertRule::total_traffic(10, 1), 10);
+        for _ in 0..20 {
+            alert.record(&log("/", 200, 10));
+        }
+        assert!(alert.evaluate(10).is_some());
+
+        alert.reconfigure(AlertRule::total_traffic(20, 1), 5);
+        assert!(alert.state.firing);
+        // Four intervals of 5s fill the new window
+        for _ in 0..3 {
+            assert!(alert.evaluate(5).is_none());
+            assert!(alert.state.firing);
+        }
+        assert!(!alert.evaluate(5).unwrap().firing);
+    }
+
+    #[test]
+    fn test_bytes_aggregations() {
+        let mut rule = AlertRule::total_traffic(30, 100);
+        rule.metric = Metric::Bytes;
+        rule.aggregation = Aggregation::Max;
+        let mut alert = Alert::new(rule, 10);
+        for size in &[500, 1500, 1000] {
+            alert.record(&log("/", 200, *size));
+            alert.evaluate(10);
+        }
+        assert_eq!(alert.state.value, 150.0);
+        assert!(alert.state.firing);
+
+        alert.rule.aggregation = Aggregation::Sum;
+        alert.record(&log("/", 200, 100));
+        alert.evaluate(10);
+        assert_eq!(alert.state.value, 2600.0);
+    }
+
+    #[test]
+    fn test_error_ratio() {
+        let mut rule = AlertRule::total_traffic(20, 2);
+        rule.metric = Metric::Ratio;
+        rule.status = vec![StatusMatcher::Class(5)];
+        rule.comparator = Comparator::Greater;
+        rule.min_requests = 100;
+        let mut alert = Alert::new(rule, 10);
+
+        for code in &[200, 200, 200, 503] {
+            alert.record(&log("/", *code, 0));
+        }
+        alert.evaluate(10);
+        alert.record(&log("/", 200, 0));
+        // 1 of 5 requests failed, but that's too little traffic to care
+        assert!(alert.evaluate(10).is_none());
+        assert_eq!(alert.state.value, 20.0);
+
+        for i in 0..100 {
+            alert.record(&log("/", if i < 3 { 500 } else { 200 }, 0));
+        }
+        // 3 of 101 requests failed
+        assert!(alert.evaluate(10).is_some());
+        assert!((alert.state.value - 2.97).abs() < 0.01);
+
+        alert.rule.aggregation = Aggregation::Max;
+        alert.record(&log("/", 200, 0));
+        alert.evaluate(10);
+        assert_eq!(alert.state.value, 3.0);
+    }
+
+    #[test]
+    fn test_grouped_alerts() {
+        let mut rule = AlertRule::total_traffic(10, 5);
+        rule.group_by = Some(GroupBy::Ip);
+        let mut alert = Alert::new(rule, 10);
+        // No key to blame without any traffic
+        assert!(alert.evaluate(10).is_none());
+        assert_eq!(alert.state.key, None);
+        for i in 0..100 {
+            let ip = format!("10.0.0.{}", i % 20);
+            alert.record(&log_from(&ip, "/", 200, 0));
+        }
+        // 100 requests in total, but only 5 per address
+        assert!(alert.evaluate(10).is_none());
+        assert_eq!(alert.state.value, 0.5);
+
+        for _ in 0..60 {
+            alert.record(&log_from("10.0.0.66", "/", 200, 0));
+        }
+        alert.record(&log_from("10.0.0.1", "/", 200, 0));
+        assert!(alert.evaluate(10).is_some());
+        assert_eq!(alert.state.value, 6.0);
+        assert_eq!(alert.state.key, Some(String::from("10.0.0.66")));
+
+        let mut rule = AlertRule::total_traffic(10, 2);
+        rule.group_by = Some(GroupBy::Section);
+        rule.comparator = Comparator::Less;
+        let mut alert = Alert::new(rule, 10);
+        for _ in 0..30 {
+            alert.record(&log("/api/users", 200, 0));
+        }
+        alert.record(&log("/health", 200, 0));
+        assert!(alert.evaluate(10).is_some());
+        assert_eq!(alert.state.key, Some(String::from("/health")));
+    }
+
+    #[test]
+    fn test_anomaly_detection() {
+        let mut rule = AlertRule::total_traffic(10, 3);
+        rule.anomaly = Some(Anomaly {
+            alpha: 0.2,
+            seasonal: false,
+            warmup: 5,
+        });
+        let mut alert = Alert::new(rule, 10);
+        // Traffic oscillating around 10req/s
+        for i in 0..20 {
+            for _ in 0..(if i % 2 == 0 { 90 } else { 110 }) {
+                alert.record(&log("/", 200, 0));
+            }
+            assert!(alert.evaluate(10).is_none());
+            assert!(!alert.state.firing);
+        }
+        // Sudden spike to 20req/s is way more than 3 standard deviations away
+        for _ in 0..200 {
+            alert.record(&log("/", 200, 0));
+        }
+        assert!(alert.evaluate(10).is_some());
+        assert!(alert.state.value > 3.0);
+
+        // Nothing at all, e.g. at night or for a new key, and then a single request
+        let mut rule = AlertRule::total_traffic(10, 3);
+        rule.anomaly = alert.rule.anomaly;
+        let mut alert = Alert::new(rule, 10);
+        for _ in 0..10 {
+            assert!(alert.evaluate(10).is_none());
+        }
+        alert.record(&log("/", 200, 0));
+        assert!(alert.evaluate(10).is_none());
+        for _ in 0..100 {
+            alert.record(&log("/", 200, 0));
+        }
+        assert!(alert.evaluate(10).is_some());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::seconds(42)), "42s");
+        assert_eq!(format_duration(Duration::seconds(62)), "1m02s");
+        assert_eq!(format_duration(Duration::seconds(3723)), "1h02m03s");
+    }
+}
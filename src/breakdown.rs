@@ -0,0 +1,139 @@
+use crate::LogEntry;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    net::IpAddr,
+    sync::Arc,
+};
+
+// Amount of the latest log lines kept for every section and address
+pub const MAX_BREAKDOWN_SAMPLES: usize = 10;
+// Amount of the busiest sections and addresses whose breakdowns outlive their interval
+pub const MAX_BREAKDOWNS: usize = 100;
+
+// Requests of a single section or client address within a refresh interval, for the drill-down
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Breakdown {
+    pub requests: u64,
+    pub bytes: u64,
+    // Full paths, e.g. "/api/users?id=1"
+    pub paths: HashMap<String, u64>,
+    pub sections: HashMap<String, u64>,
+    pub addresses: HashMap<IpAddr, u64>,
+    pub statuses: HashMap<u16, u64>,
+    pub methods: HashMap<String, u64>,
+    // The newest last, shared with the other breakdown of the same entry
+    pub log_samples: VecDeque<Arc<str>>,
+}
+
+impl Breakdown {
+    // The line is the entry formatted once for every breakdown and sample it ends up in
+    pub fn record(&mut self, log_entry: &LogEntry, line: &Arc<str>) {
+        self.requests += 1;
+        self.bytes += log_entry.response_size as u64;
+        *self
+            .paths
+            .entry(log_entry.request.path.clone())
+            .or_insert(0) += 1;
+        *self
+            .sections
+            .entry(log_entry.section().to_owned())
+            .or_insert(0) += 1;
+        *self.addresses.entry(log_entry.ip_address).or_insert(0) += 1;
+        *self.statuses.entry(log_entry.response_code).or_insert(0) += 1;
+        *self
+            .methods
+            .entry(log_entry.request.method.clone())
+            .or_insert(0) += 1;
+        if self.log_samples.len() == MAX_BREAKDOWN_SAMPLES {
+            self.log_samples.pop_front();
+        }
+        self.log_samples.push_back(Arc::clone(line));
+    }
+
+    // Percentage of requests answered with a 4xx or 5xx code
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        let errors: u64 = self
+            .statuses
+            .iter()
+            .filter(|(status, _)| **status >= 400)
+            .map(|(_, count)| count)
+            .sum();
+        errors as f64 * 100.0 / self.requests as f64
+    }
+}
+
+// Keep the breakdowns of the busiest keys only, the rest is never looked at
+pub fn busiest<K: Eq + Hash>(breakdowns: HashMap<K, Breakdown>) -> HashMap<K, Breakdown> {
+    if breakdowns.len() <= MAX_BREAKDOWNS {
+        return breakdowns;
+    }
+    let mut breakdowns = breakdowns.into_iter().collect::<Vec<_>>();
+    breakdowns.select_nth_unstable_by(MAX_BREAKDOWNS, |a, b| b.1.requests.cmp(&a.1.requests));
+    breakdowns.truncate(MAX_BREAKDOWNS);
+    breakdowns.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_log_entry;
+
+    #[test]
+    fn test_record() {
+        let mut breakdown = Breakdown::default();
+        assert_eq!(breakdown.error_rate(), 0.0);
+        for (index, (path, status)) in [
+            ("/api/users", 200),
+            ("/api/users?id=1", 503),
+            ("/api/users", 404),
+            ("/api/orders", 200),
+        ]
+        .iter()
+        .cycle()
+        .take(12)
+        .enumerate()
+        {
+            let line = format!(
+                "10.0.0.{} - - [09/May/2018:16:00:39 +0000] \"GET {} HTTP/1.0\" {} 100",
+                index % 2,
+                path,
+                status
+            );
+            let log_entry = parse_log_entry(&line).unwrap();
+            breakdown.record(&log_entry, &Arc::from(log_entry.to_string()));
+        }
+        assert_eq!(breakdown.requests, 12);
+        assert_eq!(breakdown.bytes, 1200);
+        assert_eq!(breakdown.paths["/api/users"], 6);
+        assert_eq!(breakdown.paths["/api/users?id=1"], 3);
+        assert_eq!(breakdown.sections["api"], 12);
+        assert_eq!(breakdown.addresses[&"10.0.0.1".parse().unwrap()], 6);
+        assert_eq!(breakdown.statuses[&404], 3);
+        assert_eq!(breakdown.methods["GET"], 12);
+        assert_eq!(breakdown.error_rate(), 50.0);
+        assert_eq!(breakdown.log_samples.len(), MAX_BREAKDOWN_SAMPLES);
+        assert!(breakdown.log_samples[MAX_BREAKDOWN_SAMPLES - 1].contains("/api/orders"));
+    }
+
+    #[test]
+    fn test_busiest() {
+        let breakdowns = (0..MAX_BREAKDOWNS as u64 + 10)
+            .map(|requests| {
+                let breakdown = Breakdown {
+                    requests,
+                    ..Default::default()
+                };
+                (requests, breakdown)
+            })
+            .collect::<HashMap<_, _>>();
+        let busiest = busiest(breakdowns);
+        assert_eq!(busiest.len(), MAX_BREAKDOWNS);
+        assert!(busiest.contains_key(&(MAX_BREAKDOWNS as u64 + 9)));
+        assert!(!busiest.contains_key(&9));
+        assert!(busiest.contains_key(&10));
+    }
+}
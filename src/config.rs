@@ -0,0 +1,258 @@
+use crate::{
+    AlertRule, LayoutConfig, Metric, OtlpConfig, OutputConfig, Theme, ThemeConfig, WebhookConfig,
+};
+use serde::Deserialize;
+use std::fs;
+
+// Content of the file passed with --config, every section is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub alerts: Vec<AlertRule>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub outputs: Vec<OutputConfig>,
+    pub otlp: Option<OtlpConfig>,
+    // Look of the terminal UI, --theme takes precedence
+    pub theme: Option<ThemeConfig>,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+}
+
+impl Config {
+    pub fn from_file(path: &str) -> Result<Config, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read config file {}: {}", path, err))?;
+        Config::parse(&content).map_err(|err| format!("Invalid config file {}: {}", path, err))
+    }
+
+    pub fn parse(content: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(content).map_err(|err| err.to_string())?;
+        for rule in config.alerts.iter() {
+            if rule.window == 0 {
+                return Err(format!("alert '{}' has a window of 0 seconds", rule.name));
+            }
+            if rule.metric == Metric::Ratio && rule.status.is_empty() {
+                return Err(format!(
+                    "alert '{}' has ratio metric but no status to compute the ratio of",
+                    rule.name
+                ));
+            }
+            if let Some(anomaly) = rule.anomaly {
+                if anomaly.alpha <= 0.0 || anomaly.alpha > 1.0 {
+                    return Err(format!(
+                        "alert '{}' has anomaly alpha outside of (0, 1]",
+                        rule.name
+                    ));
+                }
+            }
+        }
+        config
+            .layout
+            .validate()
+            .map_err(|err| format!("invalid layout: {}", err))?;
+        if let Some(theme) = &config.theme {
+            Theme::from_config(theme).map_err(|err| format!("invalid theme: {}", err))?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Aggregation, Anomaly, Comparator, OutputFormat, StatusMatcher, Target};
+
+    #[test]
+    fn test_parse_alert_rules() {
+        let config = Config::parse(
+            r#"
+            [[alerts]]
+            name = "High traffic"
+            window = 120
+            threshold = 10
+
+            [[alerts]]
+            name = "404 on /api"
+            metric = "requests"
+            aggregation = "sum"
+            window = 60
+            comparator = ">"
+            threshold = 100
+            section = "api"
+            status = ["404", "5xx"]
+
+            [[alerts]]
+            name = "Bandwidth"
+            metric = "bytes"
+            aggregation = "max"
+            window = 30
+            threshold = 1_000_000.0
+
+            [[alerts]]
+            name = "Errors"
+            metric = "ratio"
+            status = ["5xx"]
+            window = 120
+            comparator = ">"
+            threshold = 2
+            min_requests = 100
+
+            [[alerts]]
+            name = "Unusual traffic"
+            window = 60
+            threshold = 3
+            [alerts.anomaly]
+            seasonal = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.alerts.len(), 5);
+        assert!(config.webhooks.is_empty());
+        assert!(config.outputs.is_empty());
+        assert!(config.otlp.is_none());
+        assert_eq!(config.alerts[0], AlertRule::total_traffic(120, 10));
+        assert_eq!(config.alerts[1].aggregation, Aggregation::Sum);
+        assert_eq!(config.alerts[1].comparator, Comparator::Greater);
+        assert_eq!(config.alerts[1].section, Some(String::from("api")));
+        assert_eq!(
+            config.alerts[1].status,
+            vec![StatusMatcher::Code(404), StatusMatcher::Class(5)]
+        );
+        assert_eq!(config.alerts[2].metric, Metric::Bytes);
+        assert_eq!(config.alerts[3].metric, Metric::Ratio);
+        assert_eq!(config.alerts[3].min_requests, 100);
+        assert_eq!(
+            config.alerts[4].anomaly,
+            Some(Anomaly {
+                alpha: 0.1,
+                seasonal: true,
+                warmup: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_webhooks() {
+        let config = Config::parse(
+            r#"
+            [[webhooks]]
+            url = "http://localhost:8080/alerts"
+
+            [[webhooks]]
+            url = "https://example.com/hook"
+            timeout = 1
+            retries = 0
+            queue_size = 10
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.webhooks,
+            vec![
+                WebhookConfig {
+                    url: String::from("http://localhost:8080/alerts"),
+                    timeout: 5,
+                    retries: 3,
+                    queue_size: 100,
+                },
+                WebhookConfig {
+                    url: String::from("https://example.com/hook"),
+                    timeout: 1,
+                    retries: 0,
+                    queue_size: 10,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_outputs() {
+        let config = Config::parse(
+            r#"
+            [[outputs]]
+            format = "graphite"
+            target = "tcp://localhost:2003"
+
+            [[outputs]]
+            format = "influx"
+            target = "/var/lib/clf/metrics.txt"
+            prefix = "web"
+            tags = { env = "prod" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.outputs.len(), 2);
+        assert_eq!(config.outputs[0].format, OutputFormat::Graphite);
+        assert_eq!(
+            config.outputs[0].target,
+            Target::Tcp(String::from("localhost:2003"))
+        );
+        assert_eq!(config.outputs[0].prefix, "clf");
+        assert_eq!(config.outputs[1].format, OutputFormat::Influx);
+        assert_eq!(config.outputs[1].prefix, "web");
+        assert_eq!(config.outputs[1].tags["env"], "prod");
+        assert!(Config::parse("[[outputs]]\nformat = \"influx\"\ntarget = \"http://x\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_theme_and_layout() {
+        let config = Config::parse(
+            r#"
+            [theme]
+            base = "high-contrast"
+            accent = { fg = "magenta" }
+
+            [layout]
+            chart = 6
+            history = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.theme.unwrap().base,
+            Some(String::from("high-contrast"))
+        );
+        assert_eq!(config.layout.chart, 6);
+        assert_eq!(config.layout.history, 0);
+        assert_eq!(config.layout.left, LayoutConfig::default().left);
+        assert_eq!(Config::default().layout, LayoutConfig::default());
+
+        assert_eq!(
+            Config::parse(
+                "[layout]
+left = 150"
+            )
+            .unwrap_err(),
+            "invalid layout: layout widths are percentages, 100 at most"
+        );
+        assert_eq!(
+            Config::parse(
+                "[theme]
+ok = { fg = \"pink\" }"
+            )
+            .unwrap_err(),
+            "invalid theme: Unknown color 'pink'"
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Config::parse("[[alerts]]\nname = \"no window\"\nthreshold = 1").is_err());
+        assert!(Config::parse("[[alerts]]\nname = \"a\"\nwindow = 0\nthreshold = 1").is_err());
+        assert!(Config::parse(
+            "[[alerts]]\nname = \"a\"\nwindow = 1\nthreshold = 1\ncomparator = \"==\""
+        )
+        .is_err());
+        assert!(Config::parse(
+            "[[alerts]]\nname = \"a\"\nwindow = 1\nthreshold = 1\nstatus = [\"6xx\"]"
+        )
+        .is_err());
+        assert!(Config::parse(
+            "[[alerts]]\nname = \"a\"\nwindow = 1\nthreshold = 1\nmetric = \"ratio\""
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,503 @@
+use crate::{LogEntry, StatusMatcher};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_till, take_till1, take_while1},
+    character::complete::{char, multispace0, multispace1, satisfy},
+    combinator::{all_consuming, map, not, peek},
+    multi::many0,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use regex::Regex;
+use std::{convert::TryFrom, fmt, net::IpAddr, str::FromStr};
+
+// Expression that decides which log entries are shown, e.g.
+// status>=500 and path~^/api and ip in 10.0.0.0/8
+//
+// Conditions are "<field> <operator> <value>" and can be combined with "and", "or", "not" and
+// parentheses. Values with spaces or parentheses go into quotes
+#[derive(Clone, Debug)]
+pub struct Filter {
+    // Expression as it was typed, to show it back
+    source: String,
+    expression: Expression,
+}
+
+impl Filter {
+    pub fn matches(&self, log_entry: &LogEntry) -> bool {
+        self.expression.matches(log_entry)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let syntax = match all_consuming(terminated(or, multispace0))(source) {
+            Ok((_, syntax)) => syntax,
+            Err(nom::Err::Error(error)) | Err(nom::Err::Failure(error)) => {
+                return Err(if error.input.trim().is_empty() {
+                    String::from("Incomplete filter")
+                } else {
+                    format!("Invalid filter near '{}'", error.input.trim())
+                })
+            }
+            Err(nom::Err::Incomplete(_)) => return Err(String::from("Incomplete filter")),
+        };
+        Ok(Filter {
+            source: source.trim().to_owned(),
+            expression: Expression::try_from(syntax)?,
+        })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/* Parsing */
+
+// Expression as it was written, before the values were checked against the fields
+#[derive(Debug, PartialEq)]
+enum Syntax<'a> {
+    Or(Vec<Syntax<'a>>),
+    And(Vec<Syntax<'a>>),
+    Not(Box<Syntax<'a>>),
+    Condition(&'a str, &'a str, &'a str),
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Keyword that isn't the beginning of a longer word, e.g. "or" but not "order"
+fn keyword<'a>(word: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    delimited(
+        multispace0,
+        terminated(tag_no_case(word), not(peek(satisfy(is_word)))),
+        multispace0,
+    )
+}
+
+// One or more operands joined by the same keyword
+fn chain<'a>(
+    word: &'static str,
+    operand: fn(&'a str) -> IResult<&'a str, Syntax<'a>>,
+    join: fn(Vec<Syntax<'a>>) -> Syntax<'a>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Syntax<'a>> {
+    map(
+        pair(operand, many0(preceded(keyword(word), operand))),
+        move |(first, rest)| {
+            if rest.is_empty() {
+                first
+            } else {
+                join(std::iter::once(first).chain(rest).collect())
+            }
+        },
+    )
+}
+
+// "and" binds tighter than "or"
+fn or(input: &str) -> IResult<&str, Syntax<'_>> {
+    chain("or", and, Syntax::Or)(input)
+}
+
+fn and(input: &str) -> IResult<&str, Syntax<'_>> {
+    chain("and", unary, Syntax::And)(input)
+}
+
+fn unary(input: &str) -> IResult<&str, Syntax<'_>> {
+    alt((
+        map(preceded(keyword("not"), unary), |syntax| {
+            Syntax::Not(Box::new(syntax))
+        }),
+        delimited(
+            pair(multispace0, char('(')),
+            or,
+            pair(multispace0, char(')')),
+        ),
+        condition,
+    ))(input)
+}
+
+fn operator(input: &str) -> IResult<&str, &str> {
+    alt((
+        tag(">="),
+        tag("<="),
+        tag("!="),
+        tag("!~"),
+        tag("=="),
+        tag("="),
+        tag(">"),
+        tag("<"),
+        tag("~"),
+        terminated(tag_no_case("in"), multispace1),
+    ))(input)
+}
+
+fn value(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(char('"'), take_till(|c| c == '"'), char('"')),
+        delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+        take_till1(|c: char| c.is_whitespace() || c == '(' || c == ')'),
+    ))(input)
+}
+
+fn condition(input: &str) -> IResult<&str, Syntax<'_>> {
+    map(
+        tuple((
+            preceded(multispace0, take_while1(is_word)),
+            preceded(multispace0, operator),
+            preceded(multispace0, value),
+        )),
+        |(field, operator, value)| Syntax::Condition(field, operator, value),
+    )(input)
+}
+
+/* Evaluation */
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Ip,
+    Status,
+    Method,
+    Path,
+    Section,
+    Size,
+    User,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "ip" => Ok(Field::Ip),
+            "status" => Ok(Field::Status),
+            "method" => Ok(Field::Method),
+            "path" => Ok(Field::Path),
+            "section" => Ok(Field::Section),
+            "size" | "bytes" => Ok(Field::Size),
+            "user" => Ok(Field::User),
+            _ => Err(format!(
+                "Unknown field '{}', expected one of ip, status, method, path, section, size, user",
+                name
+            )),
+        }
+    }
+}
+
+impl Field {
+    fn text(self, log_entry: &LogEntry) -> String {
+        match self {
+            Field::Ip => log_entry.ip_address.to_string(),
+            Field::Status => log_entry.response_code.to_string(),
+            Field::Method => log_entry.request.method.clone(),
+            Field::Path => log_entry.request.path.clone(),
+            Field::Section => log_entry.section().to_owned(),
+            Field::Size => log_entry.response_size.to_string(),
+            Field::User => log_entry.user_id.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Match,
+    NotMatch,
+    In,
+}
+
+impl Operator {
+    fn parse(operator: &str) -> Self {
+        match operator.trim().to_lowercase().as_str() {
+            "!=" => Operator::NotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessOrEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterOrEqual,
+            "~" => Operator::Match,
+            "!~" => Operator::NotMatch,
+            "in" => Operator::In,
+            _ => Operator::Equal,
+        }
+    }
+}
+
+// Address range like 10.0.0.0/8, a single address is a range of its own
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Network {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(network: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid network '{}', expected e.g. 10.0.0.0/8", network);
+        let mut parts = network.splitn(2, '/');
+        let address: IpAddr = parts.next().unwrap_or("").parse().map_err(|_| invalid())?;
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Network { address, prefix })
+    }
+}
+
+impl Network {
+    fn contains(self, ip: IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Number(u128),
+    Status(StatusMatcher),
+    Text(String),
+    Regex(Regex),
+    Network(Network),
+}
+
+#[derive(Clone, Debug)]
+struct Condition {
+    field: Field,
+    operator: Operator,
+    value: Value,
+}
+
+impl Condition {
+    fn new(field: &str, symbol: &str, value: &str) -> Result<Self, String> {
+        let field = field.parse()?;
+        let operator = Operator::parse(symbol);
+        let number = || {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid number '{}'", value))
+        };
+        let value = match (field, operator) {
+            (Field::Status, Operator::Equal) | (Field::Status, Operator::NotEqual) => {
+                Value::Status(StatusMatcher::try_from(value.to_owned())?)
+            }
+            (Field::Status, Operator::Match) | (Field::Status, Operator::NotMatch) => {
+                return Err(String::from(
+                    "Status can't be matched against a pattern, use e.g. status=5xx",
+                ))
+            }
+            (Field::Status, Operator::In) | (Field::Size, Operator::In) => {
+                return Err(String::from(
+                    "'in' only works with ip, e.g. ip in 10.0.0.0/8",
+                ))
+            }
+            (Field::Status, _) | (Field::Size, _) => Value::Number(number()?),
+            (_, Operator::Match) | (_, Operator::NotMatch) => Value::Regex(
+                Regex::new(value).map_err(|err| format!("Invalid pattern '{}': {}", value, err))?,
+            ),
+            (Field::Ip, Operator::Equal)
+            | (Field::Ip, Operator::NotEqual)
+            | (Field::Ip, Operator::In) => Value::Network(value.parse()?),
+            (_, Operator::Equal) | (_, Operator::NotEqual) => Value::Text(match field {
+                Field::Section => value.trim_matches('/').to_owned(),
+                _ => value.to_owned(),
+            }),
+            _ => {
+                return Err(format!(
+                    "Operator '{}' only works with status and size",
+                    symbol
+                ))
+            }
+        };
+        Ok(Condition {
+            field,
+            operator,
+            value,
+        })
+    }
+
+    fn matches(&self, log_entry: &LogEntry) -> bool {
+        let matches = match &self.value {
+            Value::Number(number) => {
+                let value = match self.field {
+                    Field::Status => log_entry.response_code as u128,
+                    _ => log_entry.response_size,
+                };
+                match self.operator {
+                    Operator::Less => value < *number,
+                    Operator::LessOrEqual => value <= *number,
+                    Operator::Greater => value > *number,
+                    Operator::GreaterOrEqual => value >= *number,
+                    _ => value == *number,
+                }
+            }
+            Value::Status(status) => status.matches(log_entry.response_code),
+            Value::Text(text) => self.field.text(log_entry) == *text,
+            Value::Regex(regex) => regex.is_match(&self.field.text(log_entry)),
+            Value::Network(network) => network.contains(log_entry.ip_address),
+        };
+        match self.operator {
+            Operator::NotEqual | Operator::NotMatch => !matches,
+            _ => matches,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expression {
+    Or(Vec<Expression>),
+    And(Vec<Expression>),
+    Not(Box<Expression>),
+    Condition(Condition),
+}
+
+impl TryFrom<Syntax<'_>> for Expression {
+    type Error = String;
+
+    fn try_from(syntax: Syntax) -> Result<Self, Self::Error> {
+        let all = |operands: Vec<Syntax>| {
+            operands
+                .into_iter()
+                .map(Expression::try_from)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(match syntax {
+            Syntax::Or(operands) => Expression::Or(all(operands)?),
+            Syntax::And(operands) => Expression::And(all(operands)?),
+            Syntax::Not(operand) => Expression::Not(Box::new(Expression::try_from(*operand)?)),
+            Syntax::Condition(field, operator, value) => {
+                Expression::Condition(Condition::new(field, operator, value)?)
+            }
+        })
+    }
+}
+
+impl Expression {
+    fn matches(&self, log_entry: &LogEntry) -> bool {
+        match self {
+            Expression::Or(operands) => operands.iter().any(|operand| operand.matches(log_entry)),
+            Expression::And(operands) => operands.iter().all(|operand| operand.matches(log_entry)),
+            Expression::Not(operand) => !operand.matches(log_entry),
+            Expression::Condition(condition) => condition.matches(log_entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_log_entry;
+
+    fn entry(ip: &str, path: &str, status: u16) -> LogEntry {
+        parse_log_entry(&format!(
+            "{} - frank [09/May/2018:16:00:39 +0000] \"POST {} HTTP/1.0\" {} 1234",
+            ip, path, status
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            or("status>=500 and path~^/api or not (ip in 10.0.0.0/8)"),
+            Ok((
+                "",
+                Syntax::Or(vec![
+                    Syntax::And(vec![
+                        Syntax::Condition("status", ">=", "500"),
+                        Syntax::Condition("path", "~", "^/api"),
+                    ]),
+                    Syntax::Not(Box::new(Syntax::Condition("ip", "in", "10.0.0.0/8"))),
+                ])
+            ))
+        );
+        assert_eq!(
+            or("path ~ \"/a b\" AND ordered=1"),
+            Ok((
+                "",
+                Syntax::And(vec![
+                    Syntax::Condition("path", "~", "/a b"),
+                    Syntax::Condition("ordered", "=", "1"),
+                ])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter: Filter = "status>=500 and path~^/api and ip in 10.0.0.0/8"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            filter.to_string(),
+            "status>=500 and path~^/api and ip in 10.0.0.0/8"
+        );
+        assert!(filter.matches(&entry("10.1.2.3", "/api/users", 503)));
+        assert!(!filter.matches(&entry("10.1.2.3", "/api/users", 404)));
+        assert!(!filter.matches(&entry("10.1.2.3", "/static/api", 503)));
+        assert!(!filter.matches(&entry("11.1.2.3", "/api/users", 503)));
+        assert!(!filter.matches(&entry("::1", "/api/users", 503)));
+
+        let matches = |filter: &str, log_entry: &LogEntry| {
+            filter.parse::<Filter>().unwrap().matches(log_entry)
+        };
+        let log_entry = entry("192.168.1.10", "/api/users?id=1", 404);
+        for filter in &[
+            "status=4xx",
+            "status!=5xx",
+            "status=404 or status=500",
+            "not status<400",
+            "section=/api/ and method=POST",
+            "user=frank and size>1000 and bytes<=1234",
+            "ip=192.168.1.10",
+            "ip in 192.168.0.0/16 and ip!=192.168.1.11",
+            "path!~'^/static' and (method~^P or size=0)",
+            "IP in 0.0.0.0/0",
+        ] {
+            assert!(matches(filter, &log_entry), "{}", filter);
+        }
+        for filter in &["status=5xx", "ip in 192.168.2.0/24", "not method=POST"] {
+            assert!(!matches(filter, &log_entry), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |filter: &str| filter.parse::<Filter>().unwrap_err();
+        assert_eq!(error(""), "Incomplete filter");
+        assert_eq!(error("status>=500 and"), "Invalid filter near 'and'");
+        assert_eq!(error("(status=500"), "Invalid filter near '(status=500'");
+        assert!(error("host=foo").starts_with("Unknown field 'host'"));
+        assert_eq!(error("size>big"), "Invalid number 'big'");
+        assert!(error("status=6xx").starts_with("Invalid status '6xx'"));
+        assert!(error("ip in 10.0.0.0/33").starts_with("Invalid network"));
+        assert!(error("path~(").starts_with("Invalid filter near"));
+        assert!(error("path~'('").starts_with("Invalid pattern '('"));
+        assert_eq!(
+            error("path>1"),
+            "Operator '>' only works with status and size"
+        );
+    }
+}
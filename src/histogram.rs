@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+// Count and sum of values that fell into a bucket
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bucket {
+    pub count: u64,
+    pub sum: u64,
+}
+
+impl Bucket {
+    pub fn mean(&self) -> u64 {
+        self.sum.checked_div(self.count).unwrap_or(0)
+    }
+}
+
+// Histogram with exponential buckets, so it stays small no matter how many values it has seen.
+// Bucket N holds values in [2^(N-1), 2^N), bucket 0 holds zeroes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    buckets: BTreeMap<u32, Bucket>,
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: u64) {
+        let index = 64 - value.leading_zeros();
+        let bucket = self.buckets.entry(index).or_default();
+        bucket.count += 1;
+        bucket.sum += value;
+    }
+
+    // Buckets along with their upper bound (exclusive), from the lowest to the highest
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, &Bucket)> {
+        self.buckets
+            .iter()
+            .map(|(index, bucket)| (1u64.checked_shl(*index).unwrap_or(u64::MAX), bucket))
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.values().map(|bucket| bucket.count).sum()
+    }
+
+    pub fn sum(&self) -> u64 {
+        self.buckets.values().map(|bucket| bucket.sum).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::default();
+        for value in &[0, 1, 2, 3, 100, 120, 5000] {
+            histogram.record(*value);
+        }
+        assert_eq!(histogram.count(), 7);
+        assert_eq!(histogram.sum(), 5226);
+        let buckets = histogram
+            .buckets()
+            .map(|(bound, bucket)| (bound, bucket.count, bucket.mean()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            vec![
+                (1, 1, 0),
+                (2, 1, 1),
+                (4, 2, 2),
+                (128, 2, 110),
+                (8192, 1, 5000)
+            ]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::breakdown;
+use crate::{AlertEvent, AlertStatus, Breakdown, Histogram, LogEntry, UIUpdate};
+use rbl_circular_buffer::CircularBuffer;
+use std::{collections::HashMap, mem::take, net::IpAddr, sync::Arc};
+
+// Amount of log lines shown as samples
+const LOG_SAMPLES: usize = 10;
+
+// Stats aggregated over the log entries of a single refresh interval
+pub struct IntervalStats {
+    // Hashmap with {section => hit_rate}, where section is '/users'
+    stats_endpoints: HashMap<String, u64>,
+    stats_addresses: HashMap<IpAddr, u64>,
+    stats_http_codes: HashMap<u16, HashMap<String, u64>>,
+    // Breakdowns over all the response codes and methods, not only the watched ones
+    stats_statuses: HashMap<u16, u64>,
+    stats_methods: HashMap<String, u64>,
+    stats_breakdown: HashMap<(String, u16, String), u64>,
+    response_sizes: Histogram,
+    // Everything about every section and address, for the drill-down views
+    section_breakdowns: HashMap<String, Breakdown>,
+    address_breakdowns: HashMap<IpAddr, Breakdown>,
+    // Only the stats shown by the terminal UI need the breakdowns
+    pub breakdowns: bool,
+    bytes: u64,
+    parse_errors: u64,
+    // The latest log lines, unlike everything else these are kept between intervals
+    log_samples: CircularBuffer<Arc<str>>,
+}
+
+impl IntervalStats {
+    pub fn new(breakdowns: bool) -> Self {
+        IntervalStats {
+            stats_endpoints: HashMap::new(),
+            stats_addresses: HashMap::new(),
+            stats_http_codes: HashMap::new(),
+            stats_statuses: HashMap::new(),
+            stats_methods: HashMap::new(),
+            stats_breakdown: HashMap::new(),
+            response_sizes: Histogram::default(),
+            section_breakdowns: HashMap::new(),
+            address_breakdowns: HashMap::new(),
+            breakdowns,
+            bytes: 0,
+            parse_errors: 0,
+            log_samples: CircularBuffer::new(LOG_SAMPLES),
+        }
+    }
+
+    pub fn record(&mut self, log_entry: &LogEntry, http_codes: &[u16]) {
+        // Increment endpoint hits
+        *self
+            .stats_endpoints
+            .entry(log_entry.section().to_owned())
+            .or_insert(0) += 1;
+
+        // Increment IP address requests count
+        *self
+            .stats_addresses
+            .entry(log_entry.ip_address)
+            .or_insert(0) += 1;
+
+        // Increment HTTP codes requests count if the code in the list to watch
+        if http_codes.contains(&log_entry.response_code) {
+            *self
+                .stats_http_codes
+                .entry(log_entry.response_code)
+                .or_default()
+                .entry(log_entry.section().to_owned())
+                .or_insert(0) += 1;
+        }
+
+        *self
+            .stats_statuses
+            .entry(log_entry.response_code)
+            .or_insert(0) += 1;
+        *self
+            .stats_methods
+            .entry(log_entry.request.method.clone())
+            .or_insert(0) += 1;
+        *self
+            .stats_breakdown
+            .entry((
+                log_entry.section().to_owned(),
+                log_entry.response_code,
+                log_entry.request.method.clone(),
+            ))
+            .or_insert(0) += 1;
+        self.response_sizes.record(log_entry.response_size as u64);
+        // Formatted once, the samples and both breakdowns share it
+        let line: Arc<str> = Arc::from(log_entry.to_string());
+        if self.breakdowns {
+            self.section_breakdowns
+                .entry(log_entry.section().to_owned())
+                .or_default()
+                .record(log_entry, &line);
+            self.address_breakdowns
+                .entry(log_entry.ip_address)
+                .or_default()
+                .record(log_entry, &line);
+        }
+        self.bytes += log_entry.response_size as u64;
+
+        // Collect 10 Log samples into FIFO buffer of limited capacity
+        self.log_samples.push(line);
+    }
+
+    pub fn record_error(&mut self) {
+        self.parse_errors += 1;
+    }
+
+    // Stats of the interval that just ended, the next interval starts from scratch
+    pub fn finish(
+        &mut self,
+        refresh_interval: u64,
+        alerts: Vec<AlertStatus>,
+        alert_events: Vec<AlertEvent>,
+    ) -> UIUpdate {
+        let total = self.stats_endpoints.values().sum::<u64>();
+        UIUpdate {
+            stats_endpoints: take(&mut self.stats_endpoints),
+            avg_rate: total / refresh_interval,
+            stats_addresses: take(&mut self.stats_addresses),
+            log_samples: self
+                .log_samples
+                .to_owned()
+                .map(|line| line.to_string())
+                .collect::<Vec<String>>(),
+            alerts,
+            alert_events,
+            stats_http_codes: take(&mut self.stats_http_codes),
+            stats_statuses: take(&mut self.stats_statuses),
+            stats_methods: take(&mut self.stats_methods),
+            stats_breakdown: take(&mut self.stats_breakdown),
+            response_sizes: take(&mut self.response_sizes),
+            section_breakdowns: breakdown::busiest(take(&mut self.section_breakdowns)),
+            address_breakdowns: breakdown::busiest(take(&mut self.address_breakdowns)),
+            requests: total,
+            bytes: take(&mut self.bytes),
+            parse_errors: take(&mut self.parse_errors),
+            refresh_interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_log_entry;
+
+    #[test]
+    fn test_finish() {
+        let mut stats = IntervalStats::new(true);
+        for (ip, path, status) in &[
+            ("10.0.0.1", "/api/users", 200),
+            ("10.0.0.1", "/api/orders", 503),
+            ("10.0.0.2", "/static/app.js", 404),
+        ] {
+            let line = format!(
+                "{} - - [09/May/2018:16:00:39 +0000] \"GET {} HTTP/1.0\" {} 100",
+                ip, path, status
+            );
+            stats.record(&parse_log_entry(&line).unwrap(), &[404, 503]);
+        }
+        stats.record_error();
+
+        let update = stats.finish(1, vec![], vec![]);
+        assert_eq!(update.requests, 3);
+        assert_eq!(update.avg_rate, 3);
+        assert_eq!(update.bytes, 300);
+        assert_eq!(update.parse_errors, 1);
+        assert_eq!(update.stats_endpoints["api"], 2);
+        assert_eq!(update.stats_addresses[&"10.0.0.1".parse().unwrap()], 2);
+        assert_eq!(update.stats_http_codes[&503]["api"], 1);
+        assert!(!update.stats_http_codes.contains_key(&200));
+        assert_eq!(update.stats_statuses[&200], 1);
+        assert_eq!(update.section_breakdowns["api"].paths.len(), 2);
+        assert_eq!(update.log_samples.len(), 3);
+
+        // The next interval starts empty but keeps showing the latest log lines
+        let update = stats.finish(1, vec![], vec![]);
+        assert_eq!(update.requests, 0);
+        assert_eq!(update.parse_errors, 0);
+        assert!(update.stats_endpoints.is_empty());
+        assert_eq!(update.log_samples.len(), 3);
+
+        // Without a UI to show them, the breakdowns are left out
+        stats.breakdowns = false;
+        let line = "10.0.0.1 - - [09/May/2018:16:00:39 +0000] \"GET /api/users HTTP/1.0\" 200 100";
+        stats.record(&parse_log_entry(line).unwrap(), &[]);
+        let update = stats.finish(1, vec![], vec![]);
+        assert_eq!(update.requests, 1);
+        assert!(update.section_breakdowns.is_empty());
+        assert!(update.address_breakdowns.is_empty());
+    }
+}
@@ -1,23 +1,51 @@
 // Load ./parser_combinators.rs
+mod alerting;
+mod breakdown;
+mod config;
+mod filter;
+mod histogram;
+mod interval;
 mod parser_combinators;
+mod report;
+mod server;
+mod sinks;
 mod ui;
 extern crate logwatcher;
 
+pub use alerting::*;
+pub use breakdown::{Breakdown, MAX_BREAKDOWNS};
 use chrono::{Duration, Utc};
+pub use config::Config;
+pub use filter::Filter;
+pub use histogram::{Bucket, Histogram};
+use interval::IntervalStats;
 use logwatcher::{LogWatcher, LogWatcherAction};
 pub use parser_combinators::*;
-use rbl_circular_buffer::*;
-use std::process::exit;
+pub use report::{parse_since, Report, ReportFormat};
+pub use server::spawn_server;
+pub use sinks::{
+    alert_payload, event_payload, stats_payload, CommandHook, Database, HistoryFile, JsonLines,
+    Otlp, OtlpConfig, Output, OutputConfig, OutputFormat, Sink, StatsD, Storage, Target, Webhook,
+    WebhookConfig,
+};
 use std::{
-    collections::HashMap,
+    any::Any,
     io,
     io::BufRead,
-    net::IpAddr,
-    sync::mpsc::{Receiver, SendError, Sender},
+    panic::{self, Location},
+    sync::mpsc::{Receiver, RecvTimeoutError, SendError, Sender},
+    thread,
 };
 use termion::{event::Key, input::TermRead};
 
-pub use ui::{draw, init_ui, RenderMessage, UIUpdate};
+pub use ui::{
+    draw, init_ui, LayoutConfig, RenderMessage, Theme, ThemeConfig, UIOptions, UIUpdate,
+    MAX_ALERT_HISTORY,
+};
+
+// Longest refresh interval in seconds, alert windows are counted in refresh intervals so it can't
+// be 0 either
+pub const MAX_REFRESH_INTERVAL: u64 = 256;
 
 // Return range of unix timestamps indicatding now() substracted by refresh interval
 // We +1 in the end because Rust ranges are not inclusive on the right side..
@@ -27,177 +55,314 @@ fn acceptible_timestamps(refresh_interval: Duration) -> std::ops::Range<i64> {
     (Utc::now().timestamp() - refresh_interval.num_seconds())..Utc::now().timestamp() + 1
 }
 
-// Read logs and send log entries through Sender (tx_stats)
+// Read logs and send log entries through Sender (tx_stats), lines that can't be parsed are sent as errors.
+// Only returns if the file can't be read, sending stops silently once the stats thread is gone
 pub fn read_logs(
     follow: bool,
-    tx_logs: Sender<LogEntry>,
+    tx_logs: Sender<Result<LogEntry, String>>,
     refresh_interval: Duration,
     filename: &str,
-) {
-    // Panicing in both cases if can't open the file
+) -> Result<(), String> {
     if !follow {
         // Initial file read on the start
         match std::fs::File::open(filename) {
             Ok(file) => {
                 let lines = std::io::BufReader::new(&file).lines();
                 for line in lines {
-                    if let Ok(unparsed_log) = line {
-                        if let Ok(log) = parsers::parse_log_entry(&unparsed_log[..]) {
+                    let unparsed_log = match line {
+                        Ok(line) => line,
+                        // Not UTF-8, skipped like any other garbage
+                        Err(err) if err.kind() == io::ErrorKind::InvalidData => continue,
+                        // Any other error would repeat forever
+                        Err(_) => break,
+                    };
+                    match parsers::parse_log_entry(&unparsed_log[..]) {
+                        Ok(log) => {
                             // Generate set of Unix timestamps according to refresh window and check
                             // if the log entry is within this timestamp
                             if acceptible_timestamps(refresh_interval)
                                 .contains(&log.timestamp.timestamp())
+                                && tx_logs.send(Ok(log)).is_err()
                             {
-                                tx_logs.send(log).unwrap();
+                                return Ok(());
+                            }
+                        }
+                        Err(err) => {
+                            if tx_logs.send(Err(err)).is_err() {
+                                return Ok(());
                             }
                         }
                     }
                 }
             }
-            Err(err) => {
-                eprintln!("Could not read the file: {}", err);
-                exit(1);
-            }
+            Err(err) => return Err(format!("Could not read the file {}: {}", filename, err)),
         }
     }
 
     // Continious file read after the file was read initially, in case it being written to continiously
-    match LogWatcher::register(filename.to_string()).as_mut() {
+    match LogWatcher::register(filename).as_mut() {
         Ok(log_watcher) => {
             log_watcher.watch(&mut move |line: String| {
-                if let Ok(log) = parsers::parse_log_entry(&line[..]) {
-                    tx_logs.send(log).unwrap();
-                }
+                // The watcher can't be stopped, the whole process is about to exit anyway
+                let _ = tx_logs.send(parsers::parse_log_entry(&line[..]));
                 LogWatcherAction::None
             });
+            Ok(())
         }
-        Err(err) => {
-            println!("Could not read the file: {}", err);
-            exit(1);
-        }
+        Err(err) => Err(format!("Could not watch the file {}: {}", filename, err)),
     }
 }
 
+// Changes to the stats thread requested from the UI at runtime
+#[derive(Clone, Debug)]
+pub enum Control {
+    // Only show log entries matching the filter in the UI, alert rules only see them too if
+    // asked to. None shows everything again
+    Filter {
+        filter: Option<Filter>,
+        alerts: bool,
+    },
+    // Length of the refresh intervals from the current one on, in seconds
+    RefreshInterval(u64),
+    // Threshold and window of the rules watching the overall hit rate
+    AlertThreshold(f64),
+    AlertWindow(u64),
+    // HTTP response codes to reflect statistics on
+    HttpCodes(Vec<u16>),
+}
+
 // Receive logs and aggregate them to data
 pub fn collect_stats(
-    rx_logs: Receiver<LogEntry>,
-    refresh_interval: u64,
-    alerting_interval: u64,
-    alert_threshold: u64,
+    rx_logs: Receiver<Result<LogEntry, String>>,
+    mut refresh_interval: u64,
+    alert_rules: Vec<AlertRule>,
     tx_stats: Sender<RenderMessage>,
-    http_codes: Vec<u16>,
+    mut http_codes: Vec<u16>,
+    mut sinks: Vec<Box<dyn Sink>>,
+    // Missing without the terminal UI, which is also the only one showing the breakdowns
+    rx_control: Option<Receiver<Control>>,
 ) {
-    // Hashmap with {section => hit_rate}, where section is '/users'
-    let mut stats_endpoints: HashMap<String, u64> = HashMap::new();
-    let mut stats_addresses: HashMap<IpAddr, u64> = HashMap::new();
-    let mut stats_http_codes: HashMap<u16, HashMap<String, u64>> = HashMap::new();
-    let mut log_samples: CircularBuffer<String> = CircularBuffer::new(10);
-    // A circular buffer for collect alerting values
-    // pushing to CircularBuffer<T> of len that equels its max capacity results in replacing first element
-    // making it simple FIFO perfectly suitable for keeping values exclusively relevant for alerting
-    let max_alerting_buffer_len = if alerting_interval % refresh_interval != 0 {
-        alerting_interval / refresh_interval + 1
-    } else {
-        alerting_interval / refresh_interval
-    } as usize;
-    let mut alerting_buffer: CircularBuffer<u64> = CircularBuffer::new(max_alerting_buffer_len);
-    let recieve_timeout = std::time::Duration::from_secs(refresh_interval);
+    // Sinks always get the stats of all log entries..
+    let mut stats = IntervalStats::new(rx_control.is_some());
+    // ..while the UI only gets the ones matching the filter
+    let mut filter: Option<Filter> = None;
+    let mut filter_alerts = false;
+    let mut filtered_stats = IntervalStats::new(true);
+    // Every rule keeps its own window of values and its own state
+    let mut alerts: Vec<Alert> = alert_rules
+        .into_iter()
+        .map(|rule| Alert::new(rule, refresh_interval))
+        .collect();
     // Start counter for watching average values over time
     let mut start = Utc::now().timestamp() as u64;
     loop {
+        for control in rx_control.iter().flat_map(Receiver::try_iter) {
+            match control {
+                Control::Filter {
+                    filter: new_filter,
+                    alerts,
+                } => {
+                    filter = new_filter;
+                    filter_alerts = alerts;
+                    filtered_stats = IntervalStats::new(true);
+                    // The UI shows the filtered breakdowns, those of everything would go unused
+                    stats.breakdowns = filter.is_none();
+                }
+                // Rules start observing their window from scratch, samples of intervals of a
+                // different length can't be mixed
+                Control::RefreshInterval(interval) => {
+                    refresh_interval = interval;
+                    for alert in alerts.iter_mut() {
+                        alert.reconfigure(alert.rule.clone(), refresh_interval);
+                    }
+                }
+                Control::AlertThreshold(threshold) => {
+                    for alert in alerts.iter_mut().filter(|alert| alert.rule.is_total_rate()) {
+                        alert.rule.threshold = threshold;
+                    }
+                }
+                Control::AlertWindow(window) => {
+                    for alert in alerts.iter_mut().filter(|alert| alert.rule.is_total_rate()) {
+                        let rule = AlertRule {
+                            window,
+                            ..alert.rule.clone()
+                        };
+                        alert.reconfigure(rule, refresh_interval);
+                    }
+                }
+                Control::HttpCodes(codes) => http_codes = codes,
+            }
+        }
+        let recieve_timeout = std::time::Duration::from_secs(refresh_interval);
         let message = rx_logs.recv_timeout(recieve_timeout);
-        if let Ok(log_entry) = message {
-            // Increment endpoint hits
-            let count_endpoints = stats_endpoints
-                .entry(
-                    log_entry
-                        .request
-                        .path
-                        .clone()
-                        .split('/')
-                        .collect::<Vec<&str>>()[1]
-                        .to_owned(),
-                )
-                .or_insert(0);
-            *count_endpoints += 1;
-
-            // Increment IP address requests count
-            let count_addresses = stats_addresses.entry(log_entry.ip_address).or_insert(0);
-            *count_addresses += 1;
-
-            // Increment HTTP codes requests count if the code in the list to watch
-            if http_codes.iter().any(|&i| i == log_entry.response_code) {
-                let count_http_codes = stats_http_codes
-                    .entry(log_entry.response_code)
-                    .or_insert_with(HashMap::new);
-                let count_http_codes_path = count_http_codes
-                    .entry(
-                        log_entry
-                            .request
-                            .path
-                            .clone()
-                            .split('/')
-                            .collect::<Vec<&str>>()[1]
-                            .to_owned(),
-                    )
-                    .or_insert(0);
-                *count_http_codes_path += 1;
+        // The reader is gone for good, don't spin on the closed channel
+        if let Err(RecvTimeoutError::Disconnected) = message {
+            std::thread::sleep(recieve_timeout);
+        }
+        if let Ok(Err(_)) = message {
+            stats.record_error();
+            if filter.is_some() {
+                filtered_stats.record_error();
+            }
+        }
+        if let Ok(Ok(log_entry)) = message {
+            stats.record(&log_entry, &http_codes);
+            let matches = match &filter {
+                Some(filter) => filter.matches(&log_entry),
+                None => true,
             };
+            if filter.is_some() && matches {
+                filtered_stats.record(&log_entry, &http_codes);
+            }
 
-            // Collect 10 Log samples into FIFO buffer of limited capacity
-            log_samples.push(log_entry.to_string());
+            if matches || !filter_alerts {
+                for alert in alerts.iter_mut() {
+                    alert.record(&log_entry);
+                }
+            }
+            for sink in sinks.iter_mut() {
+                sink.log(&log_entry);
+            }
         }
         let end = Utc::now().timestamp() as u64;
         if (end - start) >= refresh_interval {
             start = Utc::now().timestamp() as u64;
-            let mut total = 0;
-            for value in stats_endpoints.values() {
-                total += value;
-            }
-            // Save amount of requests for the last refresh_interval to compare with alerting_threshold later
-            alerting_buffer.push(total);
-            let mut render_message = RenderMessage::UI(UIUpdate {
-                stats_endpoints,
-                avg_rate: total as u64 / refresh_interval,
-                threshold_reached: false,
-                stats_addresses: stats_addresses.clone(),
-                log_samples: log_samples.to_owned().collect::<Vec<String>>(),
-                avg_within_alert_interval: 0,
-                stats_http_codes,
+            let alert_events = alerts
+                .iter_mut()
+                .filter_map(|alert| alert.evaluate(refresh_interval))
+                .collect::<Vec<_>>();
+            let statuses = alerts.iter().map(Alert::status).collect::<Vec<_>>();
+            let filtered_update = filter.as_ref().map(|_| {
+                filtered_stats.finish(refresh_interval, statuses.clone(), alert_events.clone())
             });
-            if alerting_buffer.len() == max_alerting_buffer_len {
-                // Calculate average hits over alert_interval
-                let avg_val_in_alert_interval = alerting_buffer
-                    .collect::<Vec<u64>>()
-                    .into_iter()
-                    .sum::<u64>()
-                    / (max_alerting_buffer_len * refresh_interval as usize) as u64;
-                if avg_val_in_alert_interval >= alert_threshold as u64 {
-                    if let RenderMessage::UI(ref mut message) = render_message {
-                        message.threshold_reached = true;
-                        message.avg_within_alert_interval = avg_val_in_alert_interval;
-                    }
-                };
+            let update = stats.finish(refresh_interval, statuses, alert_events);
+            for sink in sinks.iter_mut() {
+                for event in update.alert_events.iter() {
+                    sink.alert(event);
+                }
+                sink.flush(&update);
+            }
+            // Nobody to show the stats to, the application is shutting down
+            if tx_stats
+                .send(RenderMessage::UI(Box::new(
+                    filtered_update.unwrap_or(update),
+                )))
+                .is_err()
+            {
+                return;
             }
-            tx_stats.send(render_message).unwrap();
-            // Refresh stats between refresh intervals
-            stats_endpoints = HashMap::default();
-            stats_addresses = HashMap::default();
-            stats_http_codes = HashMap::default();
         }
     }
 }
 
+// Panic of a worker thread as a fatal error, worded like the default panic hook does
+pub fn fatal_panic(
+    thread: Option<&str>,
+    payload: &(dyn Any + Send),
+    location: Option<&Location>,
+) -> RenderMessage {
+    // panic!() with a literal carries a &str, with format arguments a String
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    let location = location
+        .map(|location| format!(" at {}", location))
+        .unwrap_or_default();
+    RenderMessage::Fatal(format!(
+        "thread '{}' panicked{}:\n{}",
+        thread.unwrap_or("<unnamed>"),
+        location,
+        message
+    ))
+}
+
+// Panics of worker threads are fatal errors like any other, the UI shuts down and the message is
+// printed afterwards. A panic of the UI thread restores the terminal while unwinding, so only the
+// message has to make it out of the alternate screen
+pub fn set_panic_hook(tx_stats: Sender<RenderMessage>, ui: bool) {
+    let main_thread = thread::current().id();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let current = thread::current();
+        if current.id() != main_thread {
+            let fatal = fatal_panic(current.name(), info.payload(), info.location());
+            if tx_stats.send(fatal).is_err() {
+                default_hook(info);
+            }
+        } else if ui {
+            print!("{}{}", termion::screen::ToMainScreen, termion::cursor::Show);
+            // Still in raw mode, new lines don't return the carriage
+            eprint!("{}\r\n", info);
+        } else {
+            default_hook(info);
+        }
+    }));
+}
+
+// Every key goes to the UI, it knows whether a key is a command or typed text
 pub fn keyboard_listener(
     tx_keyboard: Sender<RenderMessage>,
 ) -> Result<(), SendError<RenderMessage>> {
     let stdin = io::stdin();
-    for evt in stdin.keys() {
-        if let Ok(key) = evt {
-            if key == Key::Char('q') {
-                tx_keyboard.send(RenderMessage::Exit)?
-            }
+    for key in stdin.keys().flatten() {
+        if key == Key::Ctrl('c') {
+            tx_keyboard.send(RenderMessage::Exit)?
+        } else {
+            tx_keyboard.send(RenderMessage::Key(key))?
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn fatal(message: RenderMessage) -> String {
+        match message {
+            RenderMessage::Fatal(err) => err,
+            _ => panic!("not a fatal error"),
+        }
+    }
+
+    #[test]
+    fn test_fatal_panic() {
+        let location = Location::caller();
+        let literal: Box<dyn Any + Send> = Box::new("boom");
+        assert_eq!(
+            fatal(fatal_panic(Some("stats"), &*literal, Some(location))),
+            format!("thread 'stats' panicked at {}:\nboom", location)
+        );
+
+        // A panicking worker, the way the hook sees it
+        let payload = thread::spawn(|| panic!("read {} lines", 3))
+            .join()
+            .unwrap_err();
+        assert_eq!(
+            fatal(fatal_panic(None, &*payload, None)),
+            "thread '<unnamed>' panicked:\nread 3 lines"
+        );
+        let other: Box<dyn Any + Send> = Box::new(42);
+        assert!(fatal(fatal_panic(None, &*other, None)).ends_with("Box<dyn Any>"));
+    }
+
+    #[test]
+    fn test_read_logs_missing_file() {
+        let (tx_logs, rx_logs) = mpsc::channel();
+        assert_eq!(
+            read_logs(
+                false,
+                tx_logs,
+                Duration::seconds(10),
+                "/nonexistent/access.log"
+            ),
+            Err(String::from(
+                "Could not read the file /nonexistent/access.log: No such file or directory (os error 2)"
+            ))
+        );
+        assert!(rx_logs.try_recv().is_err());
+    }
+}
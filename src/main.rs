@@ -1,7 +1,14 @@
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use clap::Clap;
 use clf_parser::*;
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use std::fs::OpenOptions;
 use std::io;
+use std::io::Write;
+use std::process::exit;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
@@ -13,14 +20,67 @@ use std::thread;
 #[clap(version = "0.1", author = "Ivan Kiselev. <kiselev_ivan@pm.me>")]
 struct Opts {
     /// Sets an interval of refreshing the statistics in seconds, max 256
-    #[clap(short, long, default_value = "10")]
+    #[clap(short, long, default_value = "10", parse(try_from_str = parse_refresh_interval))]
     refresh_interval: u64,
     /// Alerts after this much seconds traffic being more than threshold on average, threshold is set by --alert-threshold.
+    /// Ignored if the config file defines its own alert rules
     #[clap(long, default_value = "20")]
     alert_interval: u64,
     /// threshold for the alert
     #[clap(long, default_value = "10")]
     alert_threshold: u64,
+    /// Path to a TOML config file with named alert rules
+    #[clap(short, long)]
+    config: Option<String>,
+    /// Append every alert transition to this file
+    #[clap(long)]
+    alert_history: Option<String>,
+    /// Shell command to run when an alert fires, details of the alert are passed in ALERT_* environment variables
+    #[clap(long)]
+    on_alert_fire: Option<String>,
+    /// Shell command to run when an alert resolves, details of the alert are passed in ALERT_* environment variables
+    #[clap(long)]
+    on_alert_resolve: Option<String>,
+    /// Kill alert commands that are still running after this much seconds
+    #[clap(long, default_value = "10")]
+    alert_command_timeout: u64,
+    /// Skip alert commands while this many of them are still running
+    #[clap(long, default_value = "4")]
+    alert_command_limit: usize,
+    /// Address of the embedded HTTP server exposing Prometheus metrics on /metrics, e.g. 0.0.0.0:9100
+    #[clap(long)]
+    listen: Option<String>,
+    /// Address of a DogStatsD agent to send the stats of every refresh interval to, e.g. 127.0.0.1:8125
+    #[clap(long)]
+    statsd: Option<String>,
+    /// Prefix of the metric names sent to --statsd
+    #[clap(long, default_value = "clf")]
+    statsd_prefix: String,
+    /// Path to a SQLite database to store the aggregates of every refresh interval and alert transitions in.
+    /// Alert history is restored from it on start, the stored intervals are only there to be queried
+    #[clap(long)]
+    db: Option<String>,
+    /// Delete rows from --db that are older than this, e.g. 12h or 30d
+    #[clap(long, default_value = "7d", parse(try_from_str = parse_since))]
+    db_retention: Duration,
+    /// Amount of the busiest IPs stored in --db per refresh interval
+    #[clap(long, default_value = "10")]
+    db_top_ips: usize,
+    /// Color theme of the UI: default, monochrome, high-contrast or path to a theme file.
+    /// Overrides the [theme] section of the config file, monochrome is the default if NO_COLOR is set
+    #[clap(long)]
+    theme: Option<String>,
+    /// Apply the filter typed in the UI with '/' to the alert rules too, otherwise alerts always see
+    /// every log entry
+    #[clap(long)]
+    filter_alerts: bool,
+    /// Don't start the terminal UI, e.g. on servers without a TTY or to run as a sidecar exporter.
+    /// Stats are written to stdout as JSON lines unless --output is set
+    #[clap(long)]
+    headless: bool,
+    /// Write the stats of every refresh interval and alert transitions as JSON lines to this file
+    #[clap(short, long)]
+    output: Option<String>,
     /// Path to the file to watch
     #[clap(short, long, default_value = "/tmp/access.log")]
     filename: String,
@@ -37,64 +97,278 @@ struct Opts {
         default_value = "200,401,404,500,501,502,504"
     )]
     http_codes: Vec<u16>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clap, Clone)]
+enum Command {
+    /// Read the file once and write a static report instead of starting the live dashboard
+    Report(ReportOpts),
+}
+
+#[derive(Clap, Clone)]
+struct ReportOpts {
+    /// Path to the file to report on
+    #[clap(short, long, default_value = "/tmp/access.log")]
+    filename: String,
+    /// Only report on entries not older than this, e.g. 90s, 30m, 24h or 7d
+    #[clap(long, parse(try_from_str = parse_since))]
+    since: Option<Duration>,
+    /// Report format: markdown, html or csv
+    #[clap(long, default_value = "markdown")]
+    format: ReportFormat,
+    /// Write the report to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+    /// Amount of rows in the top sections and top IPs tables
+    #[clap(long, default_value = "10")]
+    top: usize,
+    /// Path to a TOML config file with named alert rules to find alert episodes with
+    #[clap(short, long)]
+    config: Option<String>,
+    /// Length of the intervals alert rules are evaluated over, in seconds
+    #[clap(short, long, default_value = "10", parse(try_from_str = parse_refresh_interval))]
+    refresh_interval: u64,
+    /// Window of the default alert rule, ignored if the config file defines its own alert rules
+    #[clap(long, default_value = "20")]
+    alert_interval: u64,
+    /// Threshold of the default alert rule
+    #[clap(long, default_value = "10")]
+    alert_threshold: u64,
+}
+
+fn parse_refresh_interval(value: &str) -> Result<u64, String> {
+    match value.parse::<u64>() {
+        Ok(interval) if (1..=MAX_REFRESH_INTERVAL).contains(&interval) => Ok(interval),
+        _ => Err(format!(
+            "must be a number of seconds between 1 and {}",
+            MAX_REFRESH_INTERVAL
+        )),
+    }
+}
+
+fn load_config(path: &Option<String>) -> Config {
+    match path {
+        Some(path) => Config::from_file(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        }),
+        None => Config::default(),
+    }
+}
+
+// Without rules in the config fall back to the single rule described by the flags
+fn alert_rules(config: &Config, alert_interval: u64, alert_threshold: u64) -> Vec<AlertRule> {
+    if config.alerts.is_empty() {
+        vec![AlertRule::total_traffic(alert_interval, alert_threshold)]
+    } else {
+        config.alerts.clone()
+    }
+}
+
+fn report(opts: ReportOpts) -> Result<(), io::Error> {
+    let config = load_config(&opts.config);
+    let alert_rules = alert_rules(&config, opts.alert_interval, opts.alert_threshold);
+    let since = opts.since.map(|since| Utc::now() - since);
+    let report = Report::from_file(&opts.filename, since, alert_rules, opts.refresh_interval)
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
+    let output = report.render(opts.format, opts.top);
+    match &opts.output {
+        Some(filename) => std::fs::write(filename, output),
+        None => io::stdout().write_all(output.as_bytes()),
+    }
 }
 
 fn main() -> Result<(), io::Error> {
     let opts: Opts = Opts::parse();
+    if let Some(Command::Report(report_opts)) = opts.command {
+        return report(report_opts);
+    }
+    let config = load_config(&opts.config);
+    let alert_rules = alert_rules(&config, opts.alert_interval, opts.alert_threshold);
 
     // Channel between log readre and stats producer
-    let (tx_logs, rx_logs): (Sender<LogEntry>, Receiver<LogEntry>) = mpsc::channel();
+    let (tx_logs, rx_logs) = mpsc::channel::<Result<LogEntry, String>>();
 
     //Channel between stats producer and UI renderer
     let (tx_stats, rx_stats): (Sender<RenderMessage>, Receiver<RenderMessage>) = mpsc::channel();
 
+    // Channel between UI and stats producer to change what gets aggregated
+    let (tx_control, rx_control): (Sender<Control>, Receiver<Control>) = mpsc::channel();
+
+    set_panic_hook(tx_stats.clone(), !opts.headless);
+
     // Spawn thread to read and follow the file
     // Copy parameters as they are being consumed by threads
     let refresh_interval = opts.refresh_interval;
     let filename = opts.filename.clone();
     let follow = opts.follow_only;
+    let tx_fatal = tx_stats.clone();
 
     thread::spawn(move || {
-        read_logs(
+        let result = read_logs(
             follow,
             tx_logs,
             Duration::seconds(refresh_interval as i64),
             &filename[..],
         );
+        if let Err(err) = result {
+            let _ = tx_fatal.send(RenderMessage::Fatal(err));
+        }
     });
 
     // Spawn thread to analyze logs and produce statistics
     // Copy parameters as they are being consumed by threads
     let refresh_interval = opts.refresh_interval;
-    let alerting_interval = opts.alert_interval;
-    let alerting_threshold = opts.alert_threshold;
     let stats_sender = tx_stats.clone();
-    let http_codes = opts.http_codes;
+    let http_codes = opts.http_codes.clone();
+    // Only the UI changes what gets aggregated, without it there is nothing to control
+    let rx_control = (!opts.headless).then_some(rx_control);
+    let mut sinks: Vec<Box<dyn Sink>> = vec![];
+    // Provoke instant rendering of the UI with default values instead of waiting for first cycle of refresh interval
+    let mut initial_update = UIUpdate::default();
+    if let Some(path) = &opts.db {
+        let database = Database::open(path, opts.db_retention, opts.db_top_ips)
+            .and_then(|database| {
+                initial_update.alert_events = database.alert_history(MAX_ALERT_HISTORY)?;
+                Ok(database)
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1);
+            });
+        sinks.push(Box::new(Storage::new(database)));
+    }
+    if let Some(filename) = &opts.alert_history {
+        match HistoryFile::open(filename) {
+            Ok(history) => sinks.push(Box::new(history)),
+            Err(err) => {
+                eprintln!("Could not open alert history file {}: {}", filename, err);
+                exit(1);
+            }
+        }
+    }
+    for webhook in config.webhooks {
+        sinks.push(Box::new(Webhook::new(webhook)));
+    }
+    for output in config.outputs {
+        let target = output.target.clone();
+        match Output::new(output, &opts.filename) {
+            Ok(output) => sinks.push(Box::new(output)),
+            Err(err) => {
+                eprintln!("Could not set up output to {}: {}", target, err);
+                exit(1);
+            }
+        }
+    }
+    if let Some(otlp) = config.otlp {
+        sinks.push(Box::new(Otlp::new(otlp, &opts.filename)));
+    }
+    if let Some(listen) = &opts.listen {
+        match spawn_server(listen, opts.refresh_interval, &opts.filename) {
+            Ok(mut feed) => {
+                feed.restore_history(&initial_update.alert_events);
+                sinks.push(Box::new(feed))
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                exit(1);
+            }
+        }
+    }
+    if let Some(filename) = &opts.output {
+        match OpenOptions::new().create(true).append(true).open(filename) {
+            Ok(file) => sinks.push(Box::new(JsonLines::new(Box::new(file)))),
+            Err(err) => {
+                eprintln!("Could not open output file {}: {}", filename, err);
+                exit(1);
+            }
+        }
+    } else if opts.headless {
+        sinks.push(Box::new(JsonLines::new(Box::new(io::stdout()))));
+    }
+    if let Some(addr) = &opts.statsd {
+        match StatsD::new(addr, &opts.statsd_prefix, &opts.filename) {
+            Ok(statsd) => sinks.push(Box::new(statsd)),
+            Err(err) => {
+                eprintln!("Could not set up StatsD sink for {}: {}", addr, err);
+                exit(1);
+            }
+        }
+    }
+    if opts.on_alert_fire.is_some() || opts.on_alert_resolve.is_some() {
+        sinks.push(Box::new(CommandHook::new(
+            opts.on_alert_fire,
+            opts.on_alert_resolve,
+            std::time::Duration::from_secs(opts.alert_command_timeout),
+            opts.alert_command_limit,
+        )));
+    }
     thread::spawn(move || {
         collect_stats(
             rx_logs,
             refresh_interval,
-            alerting_interval,
-            alerting_threshold,
+            alert_rules,
             stats_sender,
             http_codes,
+            sinks,
+            rx_control,
         )
     });
-    tx_stats
-        .send(RenderMessage::UI(UIUpdate::default()))
-        .unwrap();
+    if opts.headless {
+        // Stats still have to go somewhere, sinks do the actual work
+        drop(tx_stats);
+        for message in rx_stats {
+            if let RenderMessage::Fatal(err) = message {
+                eprintln!("{}", err);
+                exit(1);
+            }
+        }
+        return Ok(());
+    }
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let theme = Theme::resolve(opts.theme.as_deref(), config.theme.as_ref(), no_color)
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
+    // Killing the application has to restore the terminal just like quitting it
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    let tx_signals = tx_stats.clone();
+    thread::spawn(move || {
+        if signals.forever().next().is_some() {
+            let _ = tx_signals.send(RenderMessage::Exit);
+        }
+    });
+    let _ = tx_stats.send(RenderMessage::UI(Box::new(initial_update)));
     thread::spawn(move || keyboard_listener(tx_stats));
-    let mut screen = init_ui().unwrap();
-
-    // Provoke instant rendering of the UI with default values instead of waiting for first cycle of refresh interval
+    let mut screen = init_ui()?;
 
-    draw(
+    let result = draw(
         &mut screen,
         rx_stats,
-        opts.refresh_interval,
-        opts.alert_interval,
-        opts.alert_threshold,
-        opts.filename,
-    )?;
+        tx_control,
+        UIOptions {
+            refresh_interval: opts.refresh_interval,
+            http_codes: opts.http_codes,
+            filename: opts.filename,
+            filter_alerts: opts.filter_alerts,
+            theme,
+            layout: config.layout,
+        },
+    );
+    // Leave the alternate screen and raw mode before printing anything
+    drop(screen);
+    // Dropping the screen only writes the escape codes, they have to reach the terminal before
+    // the message does
+    io::stdout().flush()?;
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        exit(1);
+    }
     Ok(())
 }
@@ -44,6 +44,13 @@ impl Display for LogEntry {
     }
 }
 
+impl LogEntry {
+    // Section is the first segment of the path, e.g. 'users' for '/users/create'
+    pub fn section(&self) -> &str {
+        self.request.path.split('/').nth(1).unwrap_or("")
+    }
+}
+
 #[allow(dead_code)]
 pub mod parsers {
     use super::*;
@@ -0,0 +1,346 @@
+use crate::{parsers, Alert, AlertRule, Comparator, LogEntry};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io,
+    io::{BufRead, BufReader},
+    net::IpAddr,
+    str::FromStr,
+};
+
+mod render;
+
+// Output formats of the report subcommand
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(format!(
+                "unknown report format '{}', expected markdown, html or csv",
+                value
+            )),
+        }
+    }
+}
+
+// Parse durations like "90s", "30m", "24h" or "7d"
+pub fn parse_since(value: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration '{}', expected a number followed by s, m, h or d",
+            value
+        )
+    };
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let amount = value.strip_suffix(unit).ok_or_else(invalid)?;
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let scale = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    // Duration panics on anything that doesn't fit in i64 milliseconds
+    let since = amount
+        .checked_mul(scale)
+        .filter(|seconds| *seconds <= i64::MAX as u64 / 1000)
+        .map(|seconds| Duration::seconds(seconds as i64))
+        .ok_or_else(invalid)?;
+    // It gets subtracted from now, which panics before the earliest date there is
+    match Utc::now().checked_sub_signed(since) {
+        Some(_) => Ok(since),
+        None => Err(format!("duration '{}' reaches back before any date", value)),
+    }
+}
+
+// Requests of a single hour
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Traffic {
+    pub requests: u64,
+    pub bytes: u64,
+    // Responses with 5xx codes
+    pub errors: u64,
+}
+
+// Period of time an alert was firing
+#[derive(Clone, Debug, PartialEq)]
+pub struct Episode {
+    pub rule: String,
+    pub key: Option<String>,
+    pub start: DateTime<Utc>,
+    // None if the alert was still firing at the end of the log
+    pub end: Option<DateTime<Utc>>,
+    // Most extreme value while firing, in the direction of the comparator
+    pub peak: f64,
+    pub unit: &'static str,
+}
+
+// Everything that was found in the log file, rendered by Report::render
+#[derive(Default)]
+pub struct Report {
+    pub filename: String,
+    // Entries older than this were skipped
+    pub since: Option<DateTime<Utc>>,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    pub requests: u64,
+    pub bytes: u64,
+    pub parse_errors: u64,
+    pub sections: HashMap<String, u64>,
+    pub addresses: HashMap<IpAddr, u64>,
+    pub statuses: BTreeMap<u16, u64>,
+    // Keyed by the unix timestamp the hour starts at
+    pub hours: BTreeMap<i64, Traffic>,
+    pub episodes: Vec<Episode>,
+}
+
+impl Report {
+    pub fn from_file(
+        filename: &str,
+        since: Option<DateTime<Utc>>,
+        alert_rules: Vec<AlertRule>,
+        refresh_interval: u64,
+    ) -> Result<Report, String> {
+        let file = File::open(filename)
+            .map_err(|err| format!("Could not read the file {}: {}", filename, err))?;
+        // Lines that are not valid UTF-8 are counted as parse errors, reading stops on other errors
+        let lines = BufReader::new(file).lines().map_while(|line| match line {
+            Ok(line) => Some(line),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => Some(String::new()),
+            Err(_) => None,
+        });
+        let mut report = Report::from_lines(lines, since, alert_rules, refresh_interval);
+        report.filename = filename.to_owned();
+        Ok(report)
+    }
+
+    pub fn from_lines(
+        lines: impl Iterator<Item = String>,
+        since: Option<DateTime<Utc>>,
+        alert_rules: Vec<AlertRule>,
+        refresh_interval: u64,
+    ) -> Report {
+        let mut report = Report {
+            since,
+            ..Report::default()
+        };
+        let mut replay = Replay::new(alert_rules, refresh_interval);
+        for line in lines {
+            match parsers::parse_log_entry(&line) {
+                Ok(log_entry) => {
+                    let time = log_entry.timestamp.with_timezone(&Utc);
+                    if since.is_some_and(|since| time < since) {
+                        continue;
+                    }
+                    report.record(&log_entry, time);
+                    replay.record(&log_entry, time);
+                }
+                Err(_) => report.parse_errors += 1,
+            }
+        }
+        report.episodes = replay.finish();
+        report
+    }
+
+    fn record(&mut self, log_entry: &LogEntry, time: DateTime<Utc>) {
+        self.first = Some(self.first.map_or(time, |first| first.min(time)));
+        self.last = Some(self.last.map_or(time, |last| last.max(time)));
+        self.requests += 1;
+        self.bytes += log_entry.response_size as u64;
+        *self
+            .sections
+            .entry(log_entry.section().to_owned())
+            .or_insert(0) += 1;
+        *self.addresses.entry(log_entry.ip_address).or_insert(0) += 1;
+        *self.statuses.entry(log_entry.response_code).or_insert(0) += 1;
+        let hour = time.timestamp() - time.timestamp().rem_euclid(3600);
+        let traffic = self.hours.entry(hour).or_default();
+        traffic.requests += 1;
+        traffic.bytes += log_entry.response_size as u64;
+        if log_entry.response_code / 100 == 5 {
+            traffic.errors += 1;
+        }
+    }
+}
+
+// Evaluates alert rules over the log as if it was followed live, with refresh intervals
+// following the timestamps of the entries. Entries are expected to be roughly in order,
+// late ones are counted to the interval that is open when they are read
+struct Replay {
+    alerts: Vec<Alert>,
+    refresh_interval: u64,
+    // Unix timestamp the open refresh interval ends at
+    interval_end: Option<i64>,
+    // Empty intervals to evaluate after a gap in the log, enough for every window to drain
+    max_gap: u64,
+    episodes: Vec<Episode>,
+    // Index of the open episode of every alert
+    open: HashMap<usize, usize>,
+}
+
+impl Replay {
+    fn new(alert_rules: Vec<AlertRule>, refresh_interval: u64) -> Self {
+        let refresh_interval = refresh_interval.max(1);
+        let max_gap = alert_rules
+            .iter()
+            .map(|rule| rule.window.div_ceil(refresh_interval) + 1)
+            .max()
+            .unwrap_or(1);
+        Replay {
+            alerts: alert_rules
+                .into_iter()
+                .map(|rule| Alert::new(rule, refresh_interval))
+                .collect(),
+            refresh_interval,
+            interval_end: None,
+            max_gap,
+            episodes: vec![],
+            open: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, log_entry: &LogEntry, time: DateTime<Utc>) {
+        let refresh = self.refresh_interval as i64;
+        let time = time.timestamp();
+        let mut end = self
+            .interval_end
+            .unwrap_or(time - time.rem_euclid(refresh) + refresh);
+        let mut steps = 0;
+        while time >= end {
+            if steps > self.max_gap {
+                // Nothing changes anymore during a long silence, jump right to the entry
+                end = time - time.rem_euclid(refresh) + refresh;
+                break;
+            }
+            self.evaluate(end);
+            end += refresh;
+            steps += 1;
+        }
+        self.interval_end = Some(end);
+        for alert in self.alerts.iter_mut() {
+            alert.record(log_entry);
+        }
+    }
+
+    fn evaluate(&mut self, end: i64) {
+        let time = Utc.timestamp(end, 0);
+        for (index, alert) in self.alerts.iter_mut().enumerate() {
+            if let Some(event) = alert.evaluate_at(self.refresh_interval, time) {
+                if event.firing {
+                    self.open.insert(index, self.episodes.len());
+                    self.episodes.push(Episode {
+                        rule: event.name,
+                        key: event.key,
+                        start: event.time,
+                        end: None,
+                        peak: event.value,
+                        unit: event.unit,
+                    });
+                } else if let Some(open) = self.open.remove(&index) {
+                    self.episodes[open].end = Some(event.time);
+                }
+            } else if let Some(open) = self.open.get(&index) {
+                let episode = &mut self.episodes[*open];
+                let value = alert.state.value;
+                episode.peak = match alert.rule.comparator {
+                    Comparator::Less | Comparator::LessOrEqual => episode.peak.min(value),
+                    _ => episode.peak.max(value),
+                };
+            }
+        }
+    }
+
+    // Close the last interval, alerts that are still firing stay open
+    fn finish(mut self) -> Vec<Episode> {
+        if let Some(end) = self.interval_end {
+            self.evaluate(end);
+        }
+        self.episodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(time: &str, path: &str, code: u16) -> String {
+        format!(
+            "10.0.0.1 - - [{} +0000] \"GET {} HTTP/1.1\" {} 100",
+            time, path, code
+        )
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_since("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_since("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_since("7d"), Ok(Duration::days(7)));
+        assert!(parse_since("h").is_err());
+        assert!(parse_since("24").is_err());
+        assert!(parse_since("1w").is_err());
+        assert!(parse_since("5ü").is_err());
+        assert!(parse_since("-5m").is_err());
+        assert!(parse_since("").is_err());
+        assert!(parse_since("99999999999999999d").is_err());
+        assert!(parse_since("100000000d").is_err());
+        assert_eq!(parse_since("36500d"), Ok(Duration::days(36500)));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut lines = vec![
+            line("10/Oct/2000:12:59:00", "/old", 200),
+            String::from("garbage"),
+        ];
+        // 5 req/s for a minute, then a quiet minute
+        for second in 0..60 {
+            for _ in 0..5 {
+                lines.push(line(
+                    &format!("10/Oct/2000:13:00:{:02}", second),
+                    "/api/users",
+                    if second % 2 == 0 { 200 } else { 503 },
+                ));
+            }
+        }
+        lines.push(line("10/Oct/2000:13:02:00", "/static/app.js", 404));
+
+        let report = Report::from_lines(
+            lines.into_iter(),
+            Some(Utc.ymd(2000, 10, 10).and_hms(13, 0, 0)),
+            vec![AlertRule::total_traffic(20, 4)],
+            10,
+        );
+        assert_eq!(report.requests, 301);
+        assert_eq!(report.parse_errors, 1);
+        assert_eq!(report.sections["api"], 300);
+        assert_eq!(report.sections["static"], 1);
+        assert_eq!(report.statuses[&503], 150);
+        assert_eq!(report.hours.len(), 1);
+        assert_eq!(report.hours.values().next().unwrap().errors, 150);
+        assert_eq!(
+            report.episodes,
+            vec![Episode {
+                rule: String::from("High traffic"),
+                key: None,
+                start: Utc.ymd(2000, 10, 10).and_hms(13, 0, 20),
+                end: Some(Utc.ymd(2000, 10, 10).and_hms(13, 1, 10)),
+                peak: 5.0,
+                unit: "req/s",
+            }]
+        );
+    }
+}
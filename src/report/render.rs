@@ -0,0 +1,314 @@
+use super::{Report, ReportFormat};
+use crate::alerting::format_duration;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use std::{collections::HashMap, fmt::Write, hash::Hash};
+
+// Every format renders the same tables, just differently
+struct Table {
+    title: &'static str,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+fn time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn share(value: u64, total: u64) -> String {
+    if total == 0 {
+        String::from("0.0%")
+    } else {
+        format!("{:.1}%", value as f64 * 100.0 / total as f64)
+    }
+}
+
+// Most frequent keys first, ties broken by the key to keep the output stable
+fn top<K: Clone + Ord + Hash>(counts: &HashMap<K, u64>, limit: usize) -> Vec<(K, u64)> {
+    let mut counts = counts
+        .iter()
+        .map(|(key, count)| (key.clone(), *count))
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
+impl Report {
+    pub fn render(&self, format: ReportFormat, limit: usize) -> String {
+        let tables = self.tables(limit);
+        match format {
+            ReportFormat::Markdown => self.markdown(&tables),
+            ReportFormat::Html => self.html(&tables),
+            ReportFormat::Csv => csv(&tables),
+        }
+    }
+
+    fn period(&self) -> String {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => format!("{} - {}", time(first), time(last)),
+            _ => String::from("no entries"),
+        }
+    }
+
+    fn tables(&self, limit: usize) -> Vec<Table> {
+        let errors = self
+            .statuses
+            .iter()
+            .filter(|(status, _)| **status / 100 == 5)
+            .map(|(_, count)| count)
+            .sum::<u64>();
+        vec![
+            Table {
+                title: "Totals",
+                headers: vec![
+                    "Requests",
+                    "Bytes",
+                    "Unique IPs",
+                    "5xx",
+                    "5xx share",
+                    "Parse errors",
+                ],
+                rows: vec![vec![
+                    self.requests.to_string(),
+                    self.bytes.to_string(),
+                    self.addresses.len().to_string(),
+                    errors.to_string(),
+                    share(errors, self.requests),
+                    self.parse_errors.to_string(),
+                ]],
+            },
+            Table {
+                title: "Top sections",
+                headers: vec!["Section", "Requests", "Share"],
+                rows: top(&self.sections, limit)
+                    .into_iter()
+                    .map(|(section, count)| {
+                        vec![
+                            format!("/{}", section),
+                            count.to_string(),
+                            share(count, self.requests),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Top IPs",
+                headers: vec!["IP", "Requests", "Share"],
+                rows: top(&self.addresses, limit)
+                    .into_iter()
+                    .map(|(address, count)| {
+                        vec![
+                            address.to_string(),
+                            count.to_string(),
+                            share(count, self.requests),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Status codes",
+                headers: vec!["Status", "Requests", "Share"],
+                rows: self
+                    .statuses
+                    .iter()
+                    .map(|(status, count)| {
+                        vec![
+                            status.to_string(),
+                            count.to_string(),
+                            share(*count, self.requests),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Hourly traffic",
+                headers: vec!["Hour", "Requests", "Bytes", "5xx"],
+                rows: self
+                    .hours
+                    .iter()
+                    .map(|(hour, traffic)| {
+                        vec![
+                            time(Utc.timestamp(*hour, 0)),
+                            traffic.requests.to_string(),
+                            traffic.bytes.to_string(),
+                            traffic.errors.to_string(),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Alert episodes",
+                headers: vec!["Rule", "Key", "Start", "End", "Duration", "Peak"],
+                rows: self
+                    .episodes
+                    .iter()
+                    .map(|episode| {
+                        vec![
+                            episode.rule.clone(),
+                            episode.key.clone().unwrap_or_default(),
+                            time(episode.start),
+                            episode
+                                .end
+                                .map(time)
+                                .unwrap_or_else(|| String::from("firing")),
+                            episode
+                                .end
+                                .map(|end| format_duration(end - episode.start))
+                                .unwrap_or_default(),
+                            format!("{:.2} {}", episode.peak, episode.unit),
+                        ]
+                    })
+                    .collect(),
+            },
+        ]
+    }
+
+    fn markdown(&self, tables: &[Table]) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Access log report\n");
+        let _ = writeln!(out, "- File: `{}`", self.filename);
+        let _ = writeln!(out, "- Period: {}", self.period());
+        if let Some(since) = self.since {
+            let _ = writeln!(out, "- Entries since: {}", time(since));
+        }
+        for table in tables {
+            let _ = writeln!(out, "\n## {}\n", table.title);
+            if table.rows.is_empty() {
+                let _ = writeln!(out, "None");
+                continue;
+            }
+            let _ = writeln!(out, "| {} |", table.headers.join(" | "));
+            let _ = writeln!(out, "|{}", " --- |".repeat(table.headers.len()));
+            for row in table.rows.iter() {
+                let row = row
+                    .iter()
+                    .map(|cell| cell.replace('|', "\\|"))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(out, "| {} |", row.join(" | "));
+            }
+        }
+        out
+    }
+
+    fn html(&self, tables: &[Table]) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Access log report</title>"
+        );
+        let _ = writeln!(
+            out,
+            "<style>body {{ font-family: sans-serif; }} table {{ border-collapse: collapse; }} \
+             th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}</style>"
+        );
+        let _ = writeln!(out, "</head>\n<body>\n<h1>Access log report</h1>\n<ul>");
+        let _ = writeln!(
+            out,
+            "<li>File: <code>{}</code></li>",
+            escape(&self.filename)
+        );
+        let _ = writeln!(out, "<li>Period: {}</li>", self.period());
+        if let Some(since) = self.since {
+            let _ = writeln!(out, "<li>Entries since: {}</li>", time(since));
+        }
+        let _ = writeln!(out, "</ul>");
+        for table in tables {
+            let _ = writeln!(out, "<h2>{}</h2>", table.title);
+            if table.rows.is_empty() {
+                let _ = writeln!(out, "<p>None</p>");
+                continue;
+            }
+            let _ = write!(out, "<table>\n<tr>");
+            for header in table.headers.iter() {
+                let _ = write!(out, "<th>{}</th>", header);
+            }
+            let _ = writeln!(out, "</tr>");
+            for row in table.rows.iter() {
+                let _ = write!(out, "<tr>");
+                for cell in row {
+                    let _ = write!(out, "<td>{}</td>", escape(cell));
+                }
+                let _ = writeln!(out, "</tr>");
+            }
+            let _ = writeln!(out, "</table>");
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// One block per table, each with its own header row, blocks separated by an empty line
+fn csv(tables: &[Table]) -> String {
+    let mut out = String::new();
+    for (index, table) in tables.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "# {}", table.title);
+        let _ = writeln!(out, "{}", table.headers.join(","));
+        for row in table.rows.iter() {
+            let row = row.iter().map(|cell| csv_field(cell)).collect::<Vec<_>>();
+            let _ = writeln!(out, "{}", row.join(","));
+        }
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        let mut report = Report {
+            filename: String::from("access.log"),
+            requests: 3,
+            ..Report::default()
+        };
+        report.sections.insert(String::from("api"), 2);
+        report.sections.insert(String::from("a,b"), 1);
+        report.statuses.insert(200, 3);
+        report
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = report().render(ReportFormat::Markdown, 10);
+        assert!(markdown.contains("- File: `access.log`"));
+        assert!(markdown.contains("## Top sections\n\n| Section | Requests | Share |\n| --- | --- | --- |\n| /api | 2 | 66.7% |\n| /a,b | 1 | 33.3% |\n"));
+        assert!(markdown.contains("## Alert episodes\n\nNone\n"));
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = report().render(ReportFormat::Csv, 1);
+        assert!(csv.contains("# Top sections\nSection,Requests,Share\n/api,2,66.7%\n\n"));
+        assert!(report()
+            .render(ReportFormat::Csv, 10)
+            .contains("\"/a,b\",1,33.3%\n"));
+    }
+
+    #[test]
+    fn test_html() {
+        let mut report = report();
+        report.filename = String::from("<script>");
+        let html = report.render(ReportFormat::Html, 10);
+        assert!(html.contains("<code>&lt;script&gt;</code>"));
+        assert!(html.contains("<tr><td>/api</td><td>2</td><td>66.7%</td></tr>"));
+    }
+}
@@ -0,0 +1,121 @@
+use super::{query_param, ServerState};
+use crate::{alert_payload, event_payload};
+use serde_json::{json, Value};
+
+pub const CONTENT_TYPE: &str = "application/json";
+
+// Value of the limit parameter of a query string like "limit=10&foo=bar"
+fn limit(query: &str) -> Option<usize> {
+    query_param(query, "limit").and_then(|value| value.parse().ok())
+}
+
+// Most hits first, ties broken by the key to keep the output stable
+fn sorted<K: ToString>(hits: impl Iterator<Item = (K, u64)>) -> Vec<(String, u64)> {
+    let mut hits = hits
+        .map(|(key, hits)| (key.to_string(), hits))
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    hits
+}
+
+// JSON body of a JSON API endpoint, None if there's no such endpoint
+pub fn route(path: &str, query: &str, state: &ServerState) -> Option<Value> {
+    let limit = limit(query);
+    let body = match path.trim_end_matches('/') {
+        "/info" => state.info.clone(),
+        "/stats/current" => state.current.clone(),
+        "/stats/sections" => sorted(
+            state
+                .sections
+                .iter()
+                .map(|(section, hits)| (section, *hits)),
+        )
+        .into_iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|(section, requests)| {
+            json!({
+                "section": format!("/{}", section),
+                "requests": requests,
+                "total": state.totals.sections.get(&section).copied().unwrap_or(0),
+            })
+        })
+        .collect(),
+        "/stats/ips" => sorted(state.addresses.iter().map(|(ip, hits)| (ip, *hits)))
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(ip, requests)| json!({ "ip": ip, "requests": requests }))
+            .collect(),
+        "/alerts" => state.alerts.iter().map(alert_payload).collect(),
+        "/alerts/history" => state
+            .history
+            .iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(event_payload)
+            .collect(),
+        _ => return None,
+    };
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::ServerFeed;
+    use crate::{AlertEvent, AlertRule, AlertStatus, Sink, UIUpdate};
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_routes() {
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let mut feed = ServerFeed {
+            state: Arc::clone(&state),
+            streams: Default::default(),
+        };
+        let mut update = UIUpdate {
+            requests: 3,
+            ..Default::default()
+        };
+        update.stats_endpoints.insert(String::from("api"), 2);
+        update.stats_endpoints.insert(String::from("static"), 1);
+        update
+            .stats_addresses
+            .insert("10.0.0.1".parse().unwrap(), 3);
+        update.alerts.push(AlertStatus {
+            rule: AlertRule::total_traffic(20, 10),
+            state: Default::default(),
+        });
+        feed.flush(&update);
+        feed.flush(&update);
+        for firing in &[true, false] {
+            feed.alert(&AlertEvent {
+                name: String::from("High traffic"),
+                firing: *firing,
+                time: Utc::now(),
+                value: 12.5,
+                threshold: 10.0,
+                unit: "req/s",
+                window: 20,
+                key: None,
+                duration: None,
+            });
+        }
+
+        let state = state.lock().unwrap();
+        let route = |path: &str, query: &str| route(path, query, &state).unwrap();
+        assert_eq!(route("/stats/current", "")["requests"], 3);
+        assert_eq!(
+            route("/stats/sections", "limit=1"),
+            json!([{"section": "/api", "requests": 2, "total": 4}])
+        );
+        assert_eq!(
+            route("/stats/ips/", ""),
+            json!([{"ip": "10.0.0.1", "requests": 3}])
+        );
+        assert_eq!(route("/alerts", "")[0]["rule"], "High traffic");
+        let history = route("/alerts/history", "");
+        assert_eq!(history.as_array().unwrap().len(), 2);
+        assert_eq!(history[0]["state"], "resolved");
+        assert!(super::route("/nope", "", &state).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    event_payload, stats_payload, AlertEvent, AlertStatus, LogEntry, Sink, UIUpdate,
+    MAX_ALERT_HISTORY,
+};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    thread,
+};
+use tiny_http::{Header, Request, Response, Server};
+
+mod api;
+mod prometheus;
+mod stream;
+
+use stream::Streams;
+
+// Counters accumulated since start, as opposed to UIUpdate that only holds the last interval
+#[derive(Debug, Default)]
+pub struct Totals {
+    pub requests: u64,
+    pub bytes: u64,
+    pub parse_errors: u64,
+    pub sections: HashMap<String, u64>,
+    pub statuses: HashMap<u16, u64>,
+    pub methods: HashMap<String, u64>,
+}
+
+impl Totals {
+    fn add(&mut self, update: &UIUpdate) {
+        self.requests += update.requests;
+        self.bytes += update.bytes;
+        self.parse_errors += update.parse_errors;
+        for (section, hits) in update.stats_endpoints.iter() {
+            *self.sections.entry(section.clone()).or_insert(0) += hits;
+        }
+        for (status, hits) in update.stats_statuses.iter() {
+            *self.statuses.entry(*status).or_insert(0) += hits;
+        }
+        for (method, hits) in update.stats_methods.iter() {
+            *self.methods.entry(method.clone()).or_insert(0) += hits;
+        }
+    }
+}
+
+// Everything the HTTP server knows about, shared between the stats thread and the server thread
+pub struct ServerState {
+    pub totals: Totals,
+    pub avg_rate: u64,
+    pub alerts: Vec<AlertStatus>,
+    // JSON of the last refresh interval, the same as written by --headless
+    pub current: Value,
+    // Hits of the last refresh interval
+    pub sections: HashMap<String, u64>,
+    pub addresses: HashMap<IpAddr, u64>,
+    // Alert transitions, newest first
+    pub history: VecDeque<AlertEvent>,
+    // Settings the stats are computed with
+    pub info: Value,
+}
+
+impl Default for ServerState {
+    fn default() -> Self {
+        let mut current = stats_payload(&UIUpdate::default());
+        current["timestamp"] = Value::Null;
+        ServerState {
+            totals: Totals::default(),
+            avg_rate: 0,
+            alerts: vec![],
+            current,
+            sections: HashMap::new(),
+            addresses: HashMap::new(),
+            history: VecDeque::new(),
+            info: Value::Null,
+        }
+    }
+}
+
+type SharedState = Arc<Mutex<ServerState>>;
+
+// Feeds the state of the HTTP server from the stats thread
+pub struct ServerFeed {
+    state: SharedState,
+    streams: Streams,
+}
+
+impl Sink for ServerFeed {
+    fn flush(&mut self, update: &UIUpdate) {
+        if let Ok(mut state) = self.state.lock() {
+            state.totals.add(update);
+            state.avg_rate = update.avg_rate;
+            state.alerts = update.alerts.clone();
+            state.current = stats_payload(update);
+            state.current["timestamp"] =
+                json!(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+            state.sections = update.stats_endpoints.clone();
+            state.addresses = update.stats_addresses.clone();
+            state.info["refresh_interval"] = json!(update.refresh_interval);
+            self.streams.broadcast("stats", &state.current);
+        }
+    }
+
+    fn alert(&mut self, event: &AlertEvent) {
+        if let Ok(mut state) = self.state.lock() {
+            state.history.push_front(event.clone());
+            state.history.truncate(MAX_ALERT_HISTORY);
+        }
+        self.streams.broadcast("alert", &event_payload(event));
+    }
+
+    fn log(&mut self, log_entry: &LogEntry) {
+        self.streams.log(log_entry);
+    }
+}
+
+impl ServerFeed {
+    // Alert transitions from before the start, oldest first
+    pub fn restore_history(&mut self, events: &[AlertEvent]) {
+        if let Ok(mut state) = self.state.lock() {
+            for event in events {
+                state.history.push_front(event.clone());
+            }
+            state.history.truncate(MAX_ALERT_HISTORY);
+        }
+    }
+}
+
+// Web UI mirroring the terminal one, fed by the event stream
+const DASHBOARD: &str = include_str!("dashboard.html");
+const DASHBOARD_TYPE: &str = "text/html; charset=utf-8";
+
+// Start serving HTTP requests in a thread of its own
+pub fn spawn_server(
+    listen: &str,
+    refresh_interval: u64,
+    filename: &str,
+) -> Result<ServerFeed, String> {
+    let server =
+        Server::http(listen).map_err(|err| format!("Could not listen on {}: {}", listen, err))?;
+    let state = SharedState::default();
+    if let Ok(mut state) = state.lock() {
+        state.info = json!({
+            "refresh_interval": refresh_interval,
+            "filename": filename,
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+    }
+    let streams = Streams::default();
+    let server_state = Arc::clone(&state);
+    let server_streams = streams.clone();
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle(request, &server_state, &server_streams);
+        }
+    });
+    Ok(ServerFeed { state, streams })
+}
+
+// Decoded value of a parameter of a query string like "limit=10&section=%2Fapi"
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        .map(percent_decode)
+        .next()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn handle(request: Request, state: &SharedState, streams: &Streams) {
+    let mut url = request.url().splitn(2, '?');
+    let path = url.next().unwrap_or("").to_owned();
+    let query = url.next().unwrap_or("").to_owned();
+    if path == "/events" {
+        streams.subscribe(request, &query);
+        return;
+    }
+    let response = match path.as_str() {
+        "/" => Response::from_string(DASHBOARD).with_header(content_type(DASHBOARD_TYPE)),
+        "/metrics" => {
+            let body = match state.lock() {
+                Ok(state) => prometheus::render(&state),
+                Err(_) => String::new(),
+            };
+            Response::from_string(body).with_header(content_type(prometheus::CONTENT_TYPE))
+        }
+        _ => match state
+            .lock()
+            .ok()
+            .and_then(|state| api::route(&path, &query, &state))
+        {
+            Some(body) => {
+                Response::from_string(body.to_string()).with_header(content_type(api::CONTENT_TYPE))
+            }
+            None => Response::from_string("Not found").with_status_code(404),
+        },
+    };
+    // Client went away, nothing to do about it
+    let _ = request.respond(response);
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    fn get(address: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_dashboard_and_info() {
+        // Borrow a free port from the OS
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let mut feed = spawn_server(&address, 10, "/tmp/access.log").unwrap();
+
+        let dashboard = get(&address, "/");
+        assert!(dashboard.starts_with("HTTP/1.1 200"));
+        assert!(dashboard.contains(DASHBOARD_TYPE));
+        assert!(dashboard.ends_with(DASHBOARD));
+
+        let info = get(&address, "/info");
+        assert!(info.contains(r#""refresh_interval":10"#));
+        assert!(info.contains(r#""filename":"/tmp/access.log""#));
+
+        // The interval changed from the settings dialog
+        let update = UIUpdate {
+            refresh_interval: 5,
+            ..Default::default()
+        };
+        feed.flush(&update);
+        assert!(get(&address, "/info").contains(r#""refresh_interval":5"#));
+    }
+}
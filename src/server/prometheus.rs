@@ -0,0 +1,165 @@
+use super::ServerState;
+use std::{collections::HashMap, fmt::Display, fmt::Write};
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// Escape label value according to the text exposition format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Write a counter with a single label, sorted by label value for stable output
+fn labeled<K: Display + Ord>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    values: &HashMap<K, u64>,
+) {
+    header(out, name, "counter", help);
+    let mut values = values.iter().collect::<Vec<_>>();
+    values.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in values {
+        let _ = writeln!(
+            out,
+            "{}{{{}=\"{}\"}} {}",
+            name,
+            label,
+            escape(&key.to_string()),
+            value
+        );
+    }
+}
+
+pub fn render(state: &ServerState) -> String {
+    let totals = &state.totals;
+    let mut out = String::new();
+    header(&mut out, "clf_requests_total", "counter", "Parsed requests");
+    let _ = writeln!(out, "clf_requests_total {}", totals.requests);
+    header(
+        &mut out,
+        "clf_response_bytes_total",
+        "counter",
+        "Bytes served in responses",
+    );
+    let _ = writeln!(out, "clf_response_bytes_total {}", totals.bytes);
+    header(
+        &mut out,
+        "clf_parse_errors_total",
+        "counter",
+        "Log lines that could not be parsed",
+    );
+    let _ = writeln!(out, "clf_parse_errors_total {}", totals.parse_errors);
+    labeled(
+        &mut out,
+        "clf_section_requests_total",
+        "Requests by section",
+        "section",
+        &totals
+            .sections
+            .iter()
+            .map(|(section, hits)| (format!("/{}", section), *hits))
+            .collect(),
+    );
+    labeled(
+        &mut out,
+        "clf_status_requests_total",
+        "Requests by response code",
+        "status",
+        &totals.statuses,
+    );
+    labeled(
+        &mut out,
+        "clf_method_requests_total",
+        "Requests by method",
+        "method",
+        &totals.methods,
+    );
+    header(
+        &mut out,
+        "clf_request_rate",
+        "gauge",
+        "Average requests per second over the last refresh interval",
+    );
+    let _ = writeln!(out, "clf_request_rate {}", state.avg_rate);
+    header(
+        &mut out,
+        "clf_alert_firing",
+        "gauge",
+        "Whether the alert rule is firing",
+    );
+    for alert in state.alerts.iter() {
+        let _ = writeln!(
+            out,
+            "clf_alert_firing{{rule=\"{}\"}} {}",
+            escape(&alert.rule.name),
+            alert.state.firing as u8
+        );
+    }
+    header(
+        &mut out,
+        "clf_alert_value",
+        "gauge",
+        "Last value the alert rule compared to its threshold",
+    );
+    for alert in state.alerts.iter() {
+        let _ = writeln!(
+            out,
+            "clf_alert_value{{rule=\"{}\"}} {}",
+            escape(&alert.rule.name),
+            alert.state.value
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertRule, AlertState, AlertStatus};
+
+    #[test]
+    fn test_render() {
+        let mut state = ServerState::default();
+        state.totals.requests = 3;
+        state.totals.bytes = 300;
+        state.totals.sections.insert(String::from("api"), 2);
+        state.totals.sections.insert(String::from("users"), 1);
+        state.totals.statuses.insert(500, 1);
+        state.totals.statuses.insert(200, 2);
+        state.totals.methods.insert(String::from("GET"), 3);
+        state.alerts.push(AlertStatus {
+            rule: AlertRule::total_traffic(120, 10),
+            state: AlertState {
+                firing: true,
+                value: 12.5,
+                ..AlertState::default()
+            },
+        });
+        let out = render(&state);
+        assert!(out.contains("# TYPE clf_requests_total counter\nclf_requests_total 3\n"));
+        assert!(out.contains("clf_response_bytes_total 300\n"));
+        assert!(out.contains(
+            "clf_section_requests_total{section=\"/api\"} 2\nclf_section_requests_total{section=\"/users\"} 1\n"
+        ));
+        assert!(out.contains(
+            "clf_status_requests_total{status=\"200\"} 2\nclf_status_requests_total{status=\"500\"} 1\n"
+        ));
+        assert!(out.contains("clf_method_requests_total{method=\"GET\"} 3\n"));
+        assert!(out.contains("clf_alert_firing{rule=\"High traffic\"} 1\n"));
+        assert!(out.contains("clf_alert_value{rule=\"High traffic\"} 12.5\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
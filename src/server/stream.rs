@@ -0,0 +1,311 @@
+use super::query_param;
+use crate::{LogEntry, StatusMatcher};
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+use std::{
+    convert::TryFrom,
+    io::Write,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tiny_http::Request;
+
+// Events waiting to be written to a single client, newer events are dropped while it's full
+const CLIENT_QUEUE_SIZE: usize = 256;
+// Clients connected at the same time, more are turned away
+const MAX_CLIENTS: usize = 64;
+// Comment sent to idle clients so proxies don't close the connection
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+// Log lines a client gets per second unless it asks for another rate
+const DEFAULT_LOG_RATE: u32 = 10;
+
+// Which log lines a client wants to see, e.g. /events?logs=true&section=api&status=5xx&rate=50
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogFilter {
+    pub section: Option<String>,
+    pub status: Option<StatusMatcher>,
+    pub ip: Option<IpAddr>,
+    // Maximum of log lines per second
+    pub rate: u32,
+}
+
+impl LogFilter {
+    // None if the client doesn't want log lines at all
+    fn from_query(query: &str) -> Result<Option<LogFilter>, String> {
+        match query_param(query, "logs").as_deref() {
+            Some("true") | Some("1") => {}
+            _ => return Ok(None),
+        }
+        let status = query_param(query, "status")
+            .map(StatusMatcher::try_from)
+            .transpose()?;
+        let ip = query_param(query, "ip")
+            .map(|ip| {
+                ip.parse()
+                    .map_err(|_| format!("Invalid IP address '{}'", ip))
+            })
+            .transpose()?;
+        let rate = query_param(query, "rate")
+            .map(|rate| {
+                rate.parse()
+                    .map_err(|_| format!("Invalid rate '{}', expected lines per second", rate))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_LOG_RATE);
+        Ok(Some(LogFilter {
+            section: query_param(query, "section")
+                .map(|section| section.trim_start_matches('/').to_owned()),
+            status,
+            ip,
+            rate,
+        }))
+    }
+
+    fn matches(&self, log_entry: &LogEntry) -> bool {
+        self.section
+            .as_ref()
+            .is_none_or(|section| log_entry.section() == section)
+            && self
+                .status
+                .is_none_or(|status| status.matches(log_entry.response_code))
+            && self.ip.is_none_or(|ip| log_entry.ip_address == ip)
+    }
+}
+
+struct Client {
+    tx_frames: SyncSender<String>,
+    logs: Option<LogFilter>,
+    // Events that didn't fit into the queue since the last one that did
+    dropped: u64,
+    // Log lines sent within the current second
+    log_window: Instant,
+    log_count: u32,
+}
+
+impl Client {
+    // False once the client is gone
+    fn send(&mut self, frame: String) -> bool {
+        if self.dropped > 0 {
+            let notice = frame_of("dropped", &json!({ "count": self.dropped }));
+            match self.tx_frames.try_send(notice) {
+                Ok(()) => self.dropped = 0,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped += 1;
+                    return true;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+        match self.tx_frames.try_send(frame) {
+            Ok(()) => true,
+            // Slow client only misses events, it doesn't slow down anyone else
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+fn frame_of(event: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+fn log_payload(log_entry: &LogEntry) -> Value {
+    json!({
+        "ip": log_entry.ip_address.to_string(),
+        "identifier": log_entry.identifier,
+        "user": log_entry.user_id,
+        "timestamp": log_entry.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "method": log_entry.request.method,
+        "path": log_entry.request.path,
+        "protocol": log_entry.request.protocol,
+        "status": log_entry.response_code,
+        "size": log_entry.response_size as u64,
+    })
+}
+
+// Server-Sent Events clients, fed from the stats thread
+#[derive(Clone, Default)]
+pub struct Streams {
+    clients: Arc<Mutex<Vec<Client>>>,
+    // Checked for every log entry without taking the lock
+    log_clients: Arc<AtomicUsize>,
+}
+
+impl Streams {
+    pub fn broadcast(&self, event: &str, data: &Value) {
+        let frame = frame_of(event, data);
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain_mut(|client| client.send(frame.clone()));
+            self.count_log_clients(&clients);
+        }
+    }
+
+    pub fn log(&self, log_entry: &LogEntry) {
+        if self.log_clients.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut frame = None;
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain_mut(|client| {
+                let filter = match &client.logs {
+                    Some(filter) if filter.matches(log_entry) => filter,
+                    _ => return true,
+                };
+                if client.log_window.elapsed() >= Duration::from_secs(1) {
+                    client.log_window = Instant::now();
+                    client.log_count = 0;
+                }
+                if client.log_count >= filter.rate {
+                    return true;
+                }
+                client.log_count += 1;
+                let frame = frame
+                    .get_or_insert_with(|| frame_of("log", &log_payload(log_entry)))
+                    .clone();
+                client.send(frame)
+            });
+            self.count_log_clients(&clients);
+        }
+    }
+
+    fn count_log_clients(&self, clients: &[Client]) {
+        let count = clients
+            .iter()
+            .filter(|client| client.logs.is_some())
+            .count();
+        self.log_clients.store(count, Ordering::Relaxed);
+    }
+
+    // Answer the request with an event stream, written by a thread of its own for as long as the client stays
+    pub fn subscribe(&self, request: Request, query: &str) {
+        let logs = match LogFilter::from_query(query) {
+            Ok(logs) => logs,
+            Err(err) => {
+                let _ =
+                    request.respond(tiny_http::Response::from_string(err).with_status_code(400));
+                return;
+            }
+        };
+        let (tx_frames, rx_frames) = sync_channel(CLIENT_QUEUE_SIZE);
+        {
+            let mut clients = match self.clients.lock() {
+                Ok(clients) => clients,
+                Err(_) => return,
+            };
+            if clients.len() >= MAX_CLIENTS {
+                let _ = request.respond(
+                    tiny_http::Response::from_string("Too many clients").with_status_code(503),
+                );
+                return;
+            }
+            clients.push(Client {
+                tx_frames,
+                logs,
+                dropped: 0,
+                log_window: Instant::now(),
+                log_count: 0,
+            });
+            self.count_log_clients(&clients);
+        }
+        // Chunked responses of tiny_http are buffered, events have to go out right away
+        let writer = request.into_writer();
+        thread::spawn(move || write_events(writer, rx_frames));
+    }
+}
+
+fn write_events(mut writer: Box<dyn Write + Send>, rx_frames: Receiver<String>) {
+    let head = "HTTP/1.1 200 OK\r\n\
+                Content-Type: text/event-stream\r\n\
+                Cache-Control: no-cache\r\n\
+                Access-Control-Allow-Origin: *\r\n\
+                Connection: close\r\n\r\n";
+    if writer
+        .write_all(head.as_bytes())
+        .and_then(|_| writer.flush())
+        .is_err()
+    {
+        return;
+    }
+    loop {
+        let chunk = match rx_frames.recv_timeout(KEEP_ALIVE) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => String::from(": keep-alive\n\n"),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        // Client went away, dropping the receiver lets the stats thread forget about it
+        if writer
+            .write_all(chunk.as_bytes())
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::parse_log_entry;
+
+    #[test]
+    fn test_log_filter() {
+        assert_eq!(LogFilter::from_query("section=api"), Ok(None));
+        let filter = LogFilter::from_query("logs=true&section=%2Fapi&status=5xx&rate=50")
+            .unwrap()
+            .unwrap();
+        assert_eq!(filter.section, Some(String::from("api")));
+        assert_eq!(filter.rate, 50);
+        assert!(LogFilter::from_query("logs=1&status=6xx").is_err());
+        assert!(LogFilter::from_query("logs=1&ip=nope").is_err());
+
+        let entry = |line: &str| parse_log_entry(line).unwrap();
+        assert!(filter.matches(&entry(
+            "10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /api/users HTTP/1.0\" 503 10"
+        )));
+        assert!(!filter.matches(&entry(
+            "10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /api/users HTTP/1.0\" 200 10"
+        )));
+        assert!(!filter.matches(&entry(
+            "10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] \"GET /static/a.js HTTP/1.0\" 503 10"
+        )));
+    }
+
+    #[test]
+    fn test_backpressure() {
+        let streams = Streams::default();
+        let (tx_frames, rx_frames) = sync_channel(2);
+        streams.clients.lock().unwrap().push(Client {
+            tx_frames,
+            logs: None,
+            dropped: 0,
+            log_window: Instant::now(),
+            log_count: 0,
+        });
+        for index in 0..5 {
+            streams.broadcast("stats", &json!(index));
+        }
+        assert_eq!(rx_frames.recv().unwrap(), "event: stats\ndata: 0\n\n");
+        assert_eq!(rx_frames.recv().unwrap(), "event: stats\ndata: 1\n\n");
+        streams.broadcast("stats", &json!(5));
+        assert_eq!(
+            rx_frames.recv().unwrap(),
+            "event: dropped\ndata: {\"count\":3}\n\n"
+        );
+        assert_eq!(rx_frames.recv().unwrap(), "event: stats\ndata: 5\n\n");
+
+        // Client that went away is forgotten with the next event
+        drop(rx_frames);
+        streams.broadcast("stats", &json!(6));
+        assert!(streams.clients.lock().unwrap().is_empty());
+    }
+}
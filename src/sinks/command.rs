@@ -0,0 +1,206 @@
+use super::Sink;
+use crate::AlertEvent;
+use chrono::SecondsFormat;
+use std::{
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Runs shell commands on alert transitions with the details of the alert in environment
+// variables: ALERT_NAME, ALERT_STATE, ALERT_VALUE, ALERT_THRESHOLD, ALERT_UNIT, ALERT_WINDOW,
+// ALERT_KEY, ALERT_TIMESTAMP and ALERT_DURATION
+pub struct CommandHook {
+    on_fire: Option<String>,
+    on_resolve: Option<String>,
+    timeout: Duration,
+    max_running: usize,
+    // Commands that are still running, shared with the threads waiting for them
+    running: Arc<AtomicUsize>,
+}
+
+impl CommandHook {
+    pub fn new(
+        on_fire: Option<String>,
+        on_resolve: Option<String>,
+        timeout: Duration,
+        max_running: usize,
+    ) -> CommandHook {
+        CommandHook {
+            on_fire,
+            on_resolve,
+            timeout,
+            max_running,
+            running: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+fn environment(event: &AlertEvent) -> Vec<(&'static str, String)> {
+    vec![
+        ("ALERT_NAME", event.name.clone()),
+        (
+            "ALERT_STATE",
+            String::from(if event.firing { "firing" } else { "resolved" }),
+        ),
+        ("ALERT_VALUE", format!("{:.2}", event.value)),
+        ("ALERT_THRESHOLD", event.threshold.to_string()),
+        ("ALERT_UNIT", String::from(event.unit)),
+        ("ALERT_WINDOW", event.window.to_string()),
+        ("ALERT_KEY", event.key.clone().unwrap_or_default()),
+        (
+            "ALERT_TIMESTAMP",
+            event.time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ),
+        (
+            "ALERT_DURATION",
+            event
+                .duration
+                .map(|duration| duration.num_seconds().to_string())
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+// Run the command and kill it if it doesn't finish in time
+fn run(command: &str, environment: Vec<(&'static str, String)>, timeout: Duration) {
+    // Output would garble the UI
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(environment)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Own process group, so whatever the shell starts can be killed along with it
+        .process_group(0)
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return,
+    };
+    let start = Instant::now();
+    while let Ok(None) = child.try_wait() {
+        if start.elapsed() >= timeout {
+            // The group id is the pid of its leader
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            let _ = child.wait();
+            return;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+impl Sink for CommandHook {
+    fn alert(&mut self, event: &AlertEvent) {
+        let command = match (event.firing, &self.on_fire, &self.on_resolve) {
+            (true, Some(command), _) | (false, _, Some(command)) => command.clone(),
+            _ => return,
+        };
+        // Skip the hook rather than piling up processes behind hung ones
+        if self.running.load(Ordering::SeqCst) >= self.max_running {
+            return;
+        }
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let running = Arc::clone(&self.running);
+        let environment = environment(event);
+        let timeout = self.timeout;
+        thread::spawn(move || {
+            run(&command, environment, timeout);
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::fs;
+
+    fn event(firing: bool) -> AlertEvent {
+        AlertEvent {
+            name: String::from("Abusive client"),
+            firing,
+            time: Utc::now(),
+            value: 60.0,
+            threshold: 50.0,
+            unit: "req/s",
+            window: 60,
+            key: Some(String::from("10.0.0.1")),
+            duration: None,
+        }
+    }
+
+    fn wait_for(running: &AtomicUsize) {
+        let start = Instant::now();
+        while running.load(Ordering::SeqCst) > 0 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_command_hook() {
+        let output = std::env::temp_dir().join(format!("clf-parser-hook-{}", std::process::id()));
+        let mut hook = CommandHook::new(
+            Some(format!(
+                "echo \"$ALERT_NAME $ALERT_STATE $ALERT_KEY $ALERT_VALUE\" > {}",
+                output.display()
+            )),
+            None,
+            Duration::from_secs(5),
+            1,
+        );
+        // No command configured for resolve transitions
+        hook.alert(&event(false));
+        assert_eq!(hook.running.load(Ordering::SeqCst), 0);
+
+        hook.alert(&event(true));
+        wait_for(&hook.running);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "Abusive client firing 10.0.0.1 60.00\n"
+        );
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_command_hook_limits() {
+        let mut hook = CommandHook::new(
+            None,
+            Some(String::from("sleep 10")),
+            Duration::from_millis(200),
+            1,
+        );
+        hook.alert(&event(false));
+        // The first command is still running
+        hook.alert(&event(false));
+        assert_eq!(hook.running.load(Ordering::SeqCst), 1);
+        // And gets killed after the timeout
+        wait_for(&hook.running);
+        assert_eq!(hook.running.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_command_hook_kills_children() {
+        let output = std::env::temp_dir().join(format!("clf-parser-orphan-{}", std::process::id()));
+        // The inner shell would outlive the outer one if only that was killed
+        let mut hook = CommandHook::new(
+            Some(format!("sh -c 'sleep 1; touch {}'; true", output.display())),
+            None,
+            Duration::from_millis(200),
+            1,
+        );
+        hook.alert(&event(true));
+        wait_for(&hook.running);
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!output.exists());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::UIUpdate;
+use chrono::{DateTime, Utc};
+
+// Graphite doesn't allow ';' in tags and the plaintext protocol is split on whitespace
+fn clean(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| match c {
+            ';' | '!' | '^' | '=' | '~' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect();
+    // Empty tag values are not allowed either
+    if value.is_empty() {
+        String::from("_")
+    } else {
+        value
+    }
+}
+
+fn series(prefix: &str, name: &str, tags: &[(String, String)], extra: &[(&str, String)]) -> String {
+    let mut series = if prefix.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", clean(prefix.trim_end_matches('.')), name)
+    };
+    for (tag, value) in tags
+        .iter()
+        .map(|(tag, value)| (tag.as_str(), value.as_str()))
+        .chain(extra.iter().map(|(tag, value)| (*tag, value.as_str())))
+    {
+        series.push_str(&format!(";{}={}", clean(tag), clean(value)));
+    }
+    series
+}
+
+// Stats of a refresh interval in Graphite plaintext protocol with tagged series
+pub fn lines(
+    update: &UIUpdate,
+    prefix: &str,
+    tags: &[(String, String)],
+    time: DateTime<Utc>,
+) -> Vec<String> {
+    let time = time.timestamp();
+    let line = |name: &str, extra: &[(&str, String)], value: String| {
+        format!("{} {} {}", series(prefix, name, tags, extra), value, time)
+    };
+    let mut lines = vec![
+        line("requests.total", &[], update.requests.to_string()),
+        line("response.bytes", &[], update.bytes.to_string()),
+        line("parse_errors", &[], update.parse_errors.to_string()),
+        line("request_rate", &[], update.avg_rate.to_string()),
+    ];
+    for ((section, status, method), count) in update.stats_breakdown.iter() {
+        lines.push(line(
+            "requests",
+            &[
+                ("section", format!("/{}", section)),
+                ("status", status.to_string()),
+                ("method", method.clone()),
+            ],
+            count.to_string(),
+        ));
+    }
+    for (bound, bucket) in update.response_sizes.buckets() {
+        let le = [("le", bound.to_string())];
+        lines.push(line("response.size.count", &le, bucket.count.to_string()));
+        lines.push(line("response.size.sum", &le, bucket.sum.to_string()));
+    }
+    for alert in update.alerts.iter() {
+        let rule = [("rule", alert.rule.name.clone())];
+        lines.push(line(
+            "alert.firing",
+            &rule,
+            (alert.state.firing as u8).to_string(),
+        ));
+        lines.push(line("alert.value", &rule, alert.state.value.to_string()));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_graphite_lines() {
+        let mut update = UIUpdate {
+            requests: 2,
+            ..Default::default()
+        };
+        update
+            .stats_breakdown
+            .insert((String::new(), 200, String::from("GET")), 2);
+        update.response_sizes.record(100);
+        let lines = lines(
+            &update,
+            "clf",
+            &[(String::from("env"), String::from("prod; test"))],
+            Utc.timestamp(1_600_000_000, 0),
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "clf.requests.total;env=prod__test 2 1600000000",
+                "clf.response.bytes;env=prod__test 0 1600000000",
+                "clf.parse_errors;env=prod__test 0 1600000000",
+                "clf.request_rate;env=prod__test 0 1600000000",
+                "clf.requests;env=prod__test;section=/;status=200;method=GET 2 1600000000",
+                "clf.response.size.count;env=prod__test;le=128 1 1600000000",
+                "clf.response.size.sum;env=prod__test;le=128 100 1600000000",
+            ]
+        );
+
+        // The prefix is cleaned like everything else
+        let prefixed = super::lines(&update, "clf prod.", &[], Utc.timestamp(1_600_000_000, 0));
+        assert_eq!(prefixed[0], "clf_prod.requests.total 2 1600000000");
+    }
+}
@@ -0,0 +1,29 @@
+use super::Sink;
+use crate::AlertEvent;
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    io::Write,
+};
+
+// Appends every alert transition to a file
+pub struct HistoryFile {
+    file: File,
+}
+
+impl HistoryFile {
+    pub fn open(filename: &str) -> Result<HistoryFile, io::Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(filename)?;
+        Ok(HistoryFile { file })
+    }
+}
+
+impl Sink for HistoryFile {
+    fn alert(&mut self, event: &AlertEvent) {
+        // Losing a line of the history is not worth stopping the stats
+        let _ = writeln!(self.file, "{}", event);
+    }
+}
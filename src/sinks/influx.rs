@@ -0,0 +1,164 @@
+use crate::UIUpdate;
+use chrono::{DateTime, Utc};
+
+// Commas and spaces have a meaning in the line protocol, equal signs only after the measurement
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            c if special.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            // Line protocol has no escape for newlines, they would end the point
+            '\n' => escaped.push_str("\\ "),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_measurement(measurement: &str) -> String {
+    escape(measurement, &[',', ' '])
+}
+
+// Tag keys, tag values and field keys
+fn escape_key(key: &str) -> String {
+    escape(key, &[',', '=', ' '])
+}
+
+fn point(
+    measurement: &str,
+    tags: &[(String, String)],
+    extra: &[(&str, String)],
+    fields: &str,
+    time: i64,
+) -> String {
+    let mut point = escape_measurement(measurement);
+    for (tag, value) in tags
+        .iter()
+        .map(|(tag, value)| (tag.as_str(), value.as_str()))
+        .chain(extra.iter().map(|(tag, value)| (*tag, value.as_str())))
+    {
+        // Empty tag values are not allowed, such tags are left out
+        if !value.is_empty() {
+            point.push_str(&format!(",{}={}", escape_key(tag), escape_key(value)));
+        }
+    }
+    format!("{} {} {}", point, fields, time)
+}
+
+// Stats of a refresh interval in InfluxDB line protocol, with nanosecond timestamps
+pub fn lines(
+    update: &UIUpdate,
+    prefix: &str,
+    tags: &[(String, String)],
+    time: DateTime<Utc>,
+) -> Vec<String> {
+    let time = time.timestamp() * 1_000_000_000;
+    let measurement = |name: &str| {
+        if prefix.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}_{}", prefix.trim_end_matches('_'), name)
+        }
+    };
+    let mut lines = vec![point(
+        &measurement("totals"),
+        tags,
+        &[],
+        &format!(
+            "requests={}i,bytes={}i,parse_errors={}i,request_rate={}i",
+            update.requests, update.bytes, update.parse_errors, update.avg_rate
+        ),
+        time,
+    )];
+    for ((section, status, method), count) in update.stats_breakdown.iter() {
+        lines.push(point(
+            &measurement("requests"),
+            tags,
+            &[
+                ("section", format!("/{}", section)),
+                ("status", status.to_string()),
+                ("method", method.clone()),
+            ],
+            &format!("count={}i", count),
+            time,
+        ));
+    }
+    for (bound, bucket) in update.response_sizes.buckets() {
+        lines.push(point(
+            &measurement("response_size"),
+            tags,
+            &[("le", bound.to_string())],
+            &format!("count={}i,sum={}i", bucket.count, bucket.sum),
+            time,
+        ));
+    }
+    for alert in update.alerts.iter() {
+        lines.push(point(
+            &measurement("alert"),
+            tags,
+            &[("rule", alert.rule.name.clone())],
+            &format!("firing={},value={}", alert.state.firing, alert.state.value),
+            time,
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertRule, AlertStatus};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_influx_lines() {
+        let mut update = UIUpdate {
+            requests: 2,
+            ..Default::default()
+        };
+        update
+            .stats_breakdown
+            .insert((String::from("api"), 503, String::from("GET")), 2);
+        update.alerts.push(AlertStatus {
+            rule: AlertRule::total_traffic(20, 10),
+            state: Default::default(),
+        });
+        let lines = lines(
+            &update,
+            "clf",
+            &[(String::from("file"), String::from("/tmp/access log"))],
+            Utc.timestamp(1_600_000_000, 0),
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "clf_totals,file=/tmp/access\\ log requests=2i,bytes=0i,parse_errors=0i,request_rate=0i 1600000000000000000",
+                "clf_requests,file=/tmp/access\\ log,section=/api,status=503,method=GET count=2i 1600000000000000000",
+                "clf_alert,file=/tmp/access\\ log,rule=High\\ traffic firing=false,value=0 1600000000000000000",
+            ]
+        );
+        assert_eq!(
+            point(
+                "a=b c",
+                &[(String::from("k=1"), String::from("v=2"))],
+                &[],
+                "n=1i",
+                0
+            ),
+            "a=b\\ c,k\\=1=v\\=2 n=1i 0"
+        );
+        assert_eq!(
+            point(
+                "a\nb",
+                &[(String::from("k\n1"), String::from("v\n2"))],
+                &[],
+                "n=1i",
+                0
+            ),
+            "a\\ b,k\\ 1=v\\ 2 n=1i 0"
+        );
+    }
+}
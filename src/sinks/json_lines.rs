@@ -0,0 +1,156 @@
+use super::{webhook::event_payload, Sink};
+use crate::{AlertEvent, AlertStatus, UIUpdate};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+
+// Writes one JSON object per line: the stats of every refresh interval and every alert transition
+pub struct JsonLines {
+    out: Box<dyn Write + Send>,
+}
+
+impl JsonLines {
+    pub fn new(out: Box<dyn Write + Send>) -> JsonLines {
+        JsonLines { out }
+    }
+
+    fn write(&mut self, value: Value) {
+        // Consumer going away is not a reason to stop the stats
+        let _ = writeln!(self.out, "{}", value).and_then(|_| self.out.flush());
+    }
+}
+
+impl Sink for JsonLines {
+    fn flush(&mut self, update: &UIUpdate) {
+        let mut value = stats_payload(update);
+        value["type"] = json!("stats");
+        value["timestamp"] = json!(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+        self.write(value);
+    }
+
+    fn alert(&mut self, event: &AlertEvent) {
+        let mut value = event_payload(event);
+        value["type"] = json!("alert");
+        self.write(value);
+    }
+}
+
+// JSON representation of the current state of an alert
+pub fn alert_payload(alert: &AlertStatus) -> Value {
+    json!({
+        "rule": alert.rule.name,
+        "state": if alert.state.firing { "firing" } else { "ok" },
+        "value": alert.state.value,
+        "threshold": alert.rule.threshold,
+        "unit": alert.rule.unit(),
+        "window": alert.rule.window,
+        "key": alert.state.key,
+        "since": alert
+            .state
+            .change_time
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    })
+}
+
+// JSON representation of the stats of a refresh interval
+pub fn stats_payload(update: &UIUpdate) -> Value {
+    let sections: Map<String, Value> = update
+        .stats_endpoints
+        .iter()
+        .map(|(section, hits)| (format!("/{}", section), json!(hits)))
+        .collect();
+    let addresses: Map<String, Value> = update
+        .stats_addresses
+        .iter()
+        .map(|(address, hits)| (address.to_string(), json!(hits)))
+        .collect();
+    let statuses: Map<String, Value> = update
+        .stats_statuses
+        .iter()
+        .map(|(status, hits)| (status.to_string(), json!(hits)))
+        .collect();
+    let response_sizes: Vec<Value> = update
+        .response_sizes
+        .buckets()
+        .map(|(bound, bucket)| json!({"le": bound, "count": bucket.count, "sum": bucket.sum}))
+        .collect();
+    // Hits per section of the response codes watched with --http-codes
+    let http_codes: Map<String, Value> = update
+        .stats_http_codes
+        .iter()
+        .map(|(code, sections)| {
+            let sections: Map<String, Value> = sections
+                .iter()
+                .map(|(section, hits)| (format!("/{}", section), json!(hits)))
+                .collect();
+            (code.to_string(), Value::Object(sections))
+        })
+        .collect();
+    let alerts: Vec<Value> = update.alerts.iter().map(alert_payload).collect();
+    json!({
+        "requests": update.requests,
+        "bytes": update.bytes,
+        "parse_errors": update.parse_errors,
+        "avg_rate": update.avg_rate,
+        "sections": sections,
+        "addresses": addresses,
+        "statuses": statuses,
+        "methods": update.stats_methods,
+        "response_sizes": response_sizes,
+        "http_codes": http_codes,
+        "log_samples": update.log_samples,
+        "alerts": alerts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // Writer that keeps everything written to it for the test to look at
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let buffer = Buffer::default();
+        let mut sink = JsonLines::new(Box::new(buffer.clone()));
+        let mut update = UIUpdate {
+            requests: 2,
+            ..Default::default()
+        };
+        update.stats_endpoints.insert(String::from("api"), 2);
+        update.stats_statuses.insert(503, 2);
+        update.response_sizes.record(100);
+        update.response_sizes.record(120);
+        sink.flush(&update);
+        sink.flush(&UIUpdate::default());
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["type"], "stats");
+        assert_eq!(lines[0]["requests"], 2);
+        assert_eq!(lines[0]["sections"]["/api"], 2);
+        assert_eq!(lines[0]["statuses"]["503"], 2);
+        assert_eq!(
+            lines[0]["response_sizes"],
+            json!([{"le": 128, "count": 2, "sum": 220}])
+        );
+        assert_eq!(lines[1]["requests"], 0);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{AlertEvent, LogEntry, UIUpdate};
+use std::{
+    io,
+    net::{ToSocketAddrs, UdpSocket},
+};
+
+mod command;
+mod graphite;
+mod history;
+mod influx;
+mod json_lines;
+mod otlp;
+mod output;
+mod statsd;
+mod storage;
+mod webhook;
+
+pub use command::CommandHook;
+pub use history::HistoryFile;
+pub use json_lines::{alert_payload, stats_payload, JsonLines};
+pub use otlp::{Otlp, OtlpConfig};
+pub use output::{Output, OutputConfig, OutputFormat, Target};
+pub use statsd::StatsD;
+pub use storage::{Database, Storage};
+pub use webhook::{event_payload, Webhook, WebhookConfig};
+
+// Something outside of the UI that wants to know what the stats thread computed.
+// Sinks are called from the stats thread, so anything that might block has to be
+// handed over to a thread of its own
+pub trait Sink: Send {
+    // Called once per refresh interval with the same data that is sent to the UI
+    fn flush(&mut self, _update: &UIUpdate) {}
+    // Called on every fire and resolve transition of an alert
+    fn alert(&mut self, _event: &AlertEvent) {}
+    // Called for every parsed log entry, has to be cheap
+    fn log(&mut self, _log_entry: &LogEntry) {}
+}
+
+// Stay below the usual MTU so datagrams don't get fragmented
+const MAX_PACKET_SIZE: usize = 1432;
+
+// UDP socket sending to the given address
+fn udp_socket(address: &str) -> io::Result<UdpSocket> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "address did not resolve"))?;
+    let bind = if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.connect(address)?;
+    Ok(socket)
+}
+
+// Join lines into as few newline separated datagrams of at most max_size bytes as possible
+fn pack(lines: Vec<String>, max_size: usize) -> Vec<String> {
+    let mut packets = vec![];
+    let mut packet = String::new();
+    for line in lines {
+        if !packet.is_empty() && packet.len() + line.len() + 1 > max_size {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(&line);
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
@@ -0,0 +1,321 @@
+use super::Sink;
+use crate::UIUpdate;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    sync::mpsc::{sync_channel, SyncSender},
+    thread,
+    time::Duration,
+};
+
+// OpenTelemetry collector receiving the stats of every refresh interval over OTLP/HTTP with JSON encoding:
+//
+// [otlp]
+// endpoint = "http://localhost:4318"
+// headers = { Authorization = "Bearer secret" }
+// resource = { "deployment.environment" = "prod" }
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OtlpConfig {
+    // Base URL of the collector, metrics are posted to /v1/metrics
+    pub endpoint: String,
+    // Seconds to wait for the collector to respond
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    // Resource attributes on top of service.name, host.name and log.file.path, can override them
+    #[serde(default)]
+    pub resource: BTreeMap<String, String>,
+    // Intervals waiting to be exported, new ones are dropped while it's full
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+fn default_queue_size() -> usize {
+    10
+}
+
+// Delta temporality, every export holds what happened since the previous one
+const AGGREGATION_TEMPORALITY_DELTA: u8 = 1;
+
+// Name of the machine for the host.name attribute
+fn host_name() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+fn attributes<'a>(attributes: impl Iterator<Item = (&'a str, Value)>) -> Value {
+    attributes
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => json!({ "stringValue": value }),
+                value => json!({ "intValue": value.to_string() }),
+            };
+            json!({ "key": key, "value": value })
+        })
+        .collect()
+}
+
+fn nanos(time: DateTime<Utc>) -> String {
+    time.timestamp_nanos().to_string()
+}
+
+pub struct Otlp {
+    resource: Value,
+    // End of the previous export, start of the next one
+    start: DateTime<Utc>,
+    tx_payloads: SyncSender<Value>,
+}
+
+impl Otlp {
+    // Spawn a thread exporting interval by interval, so a slow collector can't stall the stats
+    pub fn new(config: OtlpConfig, filename: &str) -> Otlp {
+        let mut resource = BTreeMap::new();
+        resource.insert(String::from("service.name"), String::from("clf-parser"));
+        resource.insert(String::from("host.name"), host_name());
+        resource.insert(String::from("log.file.path"), filename.to_owned());
+        resource.extend(config.resource.clone());
+        let resource = json!({
+            "attributes": attributes(
+                resource
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), json!(value)))
+            )
+        });
+
+        let (tx_payloads, rx_payloads) = sync_channel::<Value>(config.queue_size);
+        thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(config.timeout))
+                .build();
+            let url = format!("{}/v1/metrics", config.endpoint.trim_end_matches('/'));
+            for payload in rx_payloads {
+                let mut request = agent.post(&url);
+                for (name, value) in config.headers.iter() {
+                    request = request.set(name, value);
+                }
+                // There's nobody to report the failure to, the interval is lost
+                let _ = request.send_json(payload);
+            }
+        });
+        Otlp {
+            resource,
+            start: Utc::now(),
+            tx_payloads,
+        }
+    }
+
+    // ExportMetricsServiceRequest for the interval between start and end
+    fn payload(&self, update: &UIUpdate, start: DateTime<Utc>, end: DateTime<Utc>) -> Value {
+        let (start, end) = (nanos(start), nanos(end));
+        let sum = |name: &str, unit: &str, points: Vec<(Value, u64)>| {
+            let points: Vec<Value> = points
+                .into_iter()
+                .map(|(attributes, value)| {
+                    json!({
+                        "attributes": attributes,
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": end,
+                        "asInt": value.to_string(),
+                    })
+                })
+                .collect();
+            json!({
+                "name": name,
+                "unit": unit,
+                "sum": {
+                    "dataPoints": points,
+                    "aggregationTemporality": AGGREGATION_TEMPORALITY_DELTA,
+                    "isMonotonic": true,
+                },
+            })
+        };
+        let gauge = |name: &str, unit: &str, points: Vec<(Value, Value)>| {
+            let points: Vec<Value> = points
+                .into_iter()
+                .map(|(attributes, value)| {
+                    let mut point = json!({ "attributes": attributes, "timeUnixNano": end });
+                    match value {
+                        Value::Bool(value) => point["asInt"] = json!((value as u8).to_string()),
+                        value => point["asDouble"] = value,
+                    }
+                    point
+                })
+                .collect();
+            json!({ "name": name, "unit": unit, "gauge": { "dataPoints": points } })
+        };
+
+        let requests = update
+            .stats_breakdown
+            .iter()
+            .map(|((section, status, method), count)| {
+                let attributes = attributes(
+                    vec![
+                        ("section", json!(format!("/{}", section))),
+                        ("http.response.status_code", json!(status)),
+                        ("http.request.method", json!(method)),
+                    ]
+                    .into_iter(),
+                );
+                (attributes, *count)
+            })
+            .collect();
+        // Buckets hold whole numbers in [2^(N-1), 2^N), which is (2^(N-1) - 1, 2^N - 1] for OTLP
+        let buckets = update.response_sizes.buckets().collect::<Vec<_>>();
+        let mut bucket_counts = buckets
+            .iter()
+            .map(|(_, bucket)| bucket.count.to_string())
+            .collect::<Vec<_>>();
+        bucket_counts.push(String::from("0"));
+        let response_sizes = json!({
+            "name": "clf.response.size",
+            "unit": "By",
+            "histogram": {
+                "dataPoints": [{
+                    "attributes": [],
+                    "startTimeUnixNano": start,
+                    "timeUnixNano": end,
+                    "count": update.response_sizes.count().to_string(),
+                    "sum": update.response_sizes.sum(),
+                    "bucketCounts": bucket_counts,
+                    "explicitBounds": buckets.iter().map(|(bound, _)| bound - 1).collect::<Vec<_>>(),
+                }],
+                "aggregationTemporality": AGGREGATION_TEMPORALITY_DELTA,
+            },
+        });
+        let rule = |name: &str| attributes(vec![("rule", json!(name))].into_iter());
+        let metrics = vec![
+            sum("clf.requests", "{request}", requests),
+            sum("clf.response.bytes", "By", vec![(json!([]), update.bytes)]),
+            sum(
+                "clf.parse_errors",
+                "{line}",
+                vec![(json!([]), update.parse_errors)],
+            ),
+            gauge(
+                "clf.request.rate",
+                "{request}/s",
+                vec![(json!([]), json!(update.avg_rate as f64))],
+            ),
+            response_sizes,
+            gauge(
+                "clf.alert.firing",
+                "1",
+                update
+                    .alerts
+                    .iter()
+                    .map(|alert| (rule(&alert.rule.name), json!(alert.state.firing)))
+                    .collect(),
+            ),
+            gauge(
+                "clf.alert.value",
+                "1",
+                update
+                    .alerts
+                    .iter()
+                    .map(|alert| (rule(&alert.rule.name), json!(alert.state.value)))
+                    .collect(),
+            ),
+        ];
+        json!({
+            "resourceMetrics": [{
+                "resource": self.resource,
+                "scopeMetrics": [{
+                    "scope": { "name": "clf-parser", "version": "0.1" },
+                    "metrics": metrics,
+                }],
+            }],
+        })
+    }
+}
+
+impl Sink for Otlp {
+    fn flush(&mut self, update: &UIUpdate) {
+        let end = Utc::now();
+        let payload = self.payload(update, self.start, end);
+        self.start = end;
+        // Never block the stats thread, full queue means the collector can't keep up anyway
+        let _ = self.tx_payloads.try_send(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::{Response, Server};
+
+    #[test]
+    fn test_export() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let mut otlp = Otlp::new(
+            OtlpConfig {
+                endpoint: format!("http://{}/", server.server_addr()),
+                timeout: 1,
+                headers: vec![(String::from("X-Token"), String::from("secret"))]
+                    .into_iter()
+                    .collect(),
+                resource: vec![(String::from("host.name"), String::from("web-1"))]
+                    .into_iter()
+                    .collect(),
+                queue_size: 1,
+            },
+            "/tmp/access.log",
+        );
+        let mut update = UIUpdate {
+            bytes: 220,
+            ..Default::default()
+        };
+        update
+            .stats_breakdown
+            .insert((String::from("api"), 503, String::from("GET")), 2);
+        update.response_sizes.record(100);
+        update.response_sizes.record(120);
+        otlp.flush(&update);
+
+        let mut request = server
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.url(), "/v1/metrics");
+        assert!(request
+            .headers()
+            .iter()
+            .any(|header| header.field.equiv("X-Token") && header.value == "secret"));
+        let body: Value = serde_json::from_reader(request.as_reader()).unwrap();
+        request.respond(Response::empty(200)).unwrap();
+
+        let resource = &body["resourceMetrics"][0]["resource"]["attributes"];
+        assert!(resource
+            .as_array()
+            .unwrap()
+            .contains(&json!({"key": "host.name", "value": {"stringValue": "web-1"}})));
+        assert!(resource.as_array().unwrap().contains(
+            &json!({"key": "log.file.path", "value": {"stringValue": "/tmp/access.log"}})
+        ));
+        let metrics = &body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], "clf.requests");
+        let point = &metrics[0]["sum"]["dataPoints"][0];
+        assert_eq!(point["asInt"], "2");
+        assert_eq!(
+            point["attributes"][1],
+            json!({"key": "http.response.status_code", "value": {"intValue": "503"}})
+        );
+        assert_eq!(metrics[1]["sum"]["dataPoints"][0]["asInt"], "220");
+        let histogram = &metrics[4]["histogram"]["dataPoints"][0];
+        assert_eq!(histogram["count"], "2");
+        assert_eq!(histogram["bucketCounts"], json!(["2", "0"]));
+        assert_eq!(histogram["explicitBounds"], json!([127]));
+    }
+}
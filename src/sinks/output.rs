@@ -0,0 +1,254 @@
+use super::{graphite, influx, pack, udp_socket, Sink, MAX_PACKET_SIZE};
+use crate::UIUpdate;
+use chrono::Utc;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fmt,
+    fs::{File, OpenOptions},
+    io,
+    io::Write,
+    net::{TcpStream, ToSocketAddrs, UdpSocket},
+    sync::mpsc::{sync_channel, SyncSender},
+    thread,
+    time::Duration,
+};
+
+// Time to connect to and write to a TCP endpoint
+const TCP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A time series database that receives the stats of every refresh interval:
+//
+// [[outputs]]
+// format = "influx"
+// target = "udp://localhost:8089"
+// prefix = "clf"
+// tags = { env = "prod" }
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    pub format: OutputFormat,
+    pub target: Target,
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    // Added to every metric along with the log file
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    // Intervals waiting to be written, new ones are dropped while it's full
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+}
+
+fn default_prefix() -> String {
+    String::from("clf")
+}
+
+fn default_queue_size() -> usize {
+    10
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    // Graphite plaintext protocol with tagged series
+    Graphite,
+    // InfluxDB line protocol
+    Influx,
+}
+
+// Where the lines are written to: "tcp://host:port", "udp://host:port" or a file path
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Target {
+    Tcp(String),
+    Udp(String),
+    File(String),
+}
+
+impl TryFrom<String> for Target {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some(address) = value.strip_prefix("tcp://") {
+            Ok(Target::Tcp(address.to_owned()))
+        } else if let Some(address) = value.strip_prefix("udp://") {
+            Ok(Target::Udp(address.to_owned()))
+        } else if value.contains("://") {
+            Err(format!(
+                "Invalid target '{}', expected tcp://host:port, udp://host:port or a file path",
+                value
+            ))
+        } else {
+            Ok(Target::File(value))
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Tcp(address) => write!(f, "tcp://{}", address),
+            Target::Udp(address) => write!(f, "udp://{}", address),
+            Target::File(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+// Connection to the target, (re)established lazily by the writing thread
+enum Connection {
+    Tcp(Option<TcpStream>),
+    Udp(UdpSocket),
+    File(File),
+}
+
+impl Connection {
+    fn open(target: &Target) -> io::Result<Connection> {
+        match target {
+            Target::Tcp(_) => Ok(Connection::Tcp(None)),
+            Target::Udp(address) => Ok(Connection::Udp(udp_socket(address)?)),
+            Target::File(path) => Ok(Connection::File(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+        }
+    }
+
+    fn write(&mut self, target: &Target, lines: Vec<String>) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => {
+                if stream.is_none() {
+                    *stream = Some(connect(target)?);
+                }
+                let mut payload = lines.join("\n");
+                payload.push('\n');
+                let result = stream
+                    .as_mut()
+                    .map_or(Ok(()), |stream| stream.write_all(payload.as_bytes()));
+                if result.is_err() {
+                    // Reconnect with the next interval
+                    *stream = None;
+                }
+                result
+            }
+            Connection::Udp(socket) => {
+                for packet in pack(lines, MAX_PACKET_SIZE) {
+                    socket.send(packet.as_bytes())?;
+                }
+                Ok(())
+            }
+            Connection::File(file) => {
+                for line in lines {
+                    writeln!(file, "{}", line)?;
+                }
+                file.flush()
+            }
+        }
+    }
+}
+
+fn connect(target: &Target) -> io::Result<TcpStream> {
+    let address = match target {
+        Target::Tcp(address) => address,
+        _ => unreachable!("only TCP targets connect"),
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "address did not resolve"))?;
+    let stream = TcpStream::connect_timeout(&address, TCP_TIMEOUT)?;
+    stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+    Ok(stream)
+}
+
+// Formats the stats of every refresh interval in the stats thread and hands them over
+// to a thread that writes them, so a slow target can't stall the stats
+pub struct Output {
+    format: OutputFormat,
+    prefix: String,
+    tags: Vec<(String, String)>,
+    tx_lines: SyncSender<Vec<String>>,
+}
+
+impl Output {
+    pub fn new(config: OutputConfig, filename: &str) -> Result<Output, io::Error> {
+        let mut connection = Connection::open(&config.target)?;
+        let (tx_lines, rx_lines) = sync_channel::<Vec<String>>(config.queue_size);
+        let target = config.target.clone();
+        thread::spawn(move || {
+            for lines in rx_lines {
+                // There's nobody to report the failure to, the interval is lost
+                let _ = connection.write(&target, lines);
+            }
+        });
+        let mut tags = vec![(String::from("file"), filename.to_owned())];
+        tags.extend(config.tags);
+        Ok(Output {
+            format: config.format,
+            prefix: config.prefix,
+            tags,
+            tx_lines,
+        })
+    }
+}
+
+impl Sink for Output {
+    fn flush(&mut self, update: &UIUpdate) {
+        let now = Utc::now();
+        let lines = match self.format {
+            OutputFormat::Graphite => graphite::lines(update, &self.prefix, &self.tags, now),
+            OutputFormat::Influx => influx::lines(update, &self.prefix, &self.tags, now),
+        };
+        let _ = self.tx_lines.try_send(lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_target() {
+        assert_eq!(
+            Target::try_from(String::from("tcp://localhost:2003")),
+            Ok(Target::Tcp(String::from("localhost:2003")))
+        );
+        assert_eq!(
+            Target::try_from(String::from("udp://127.0.0.1:8089")),
+            Ok(Target::Udp(String::from("127.0.0.1:8089")))
+        );
+        assert_eq!(
+            Target::try_from(String::from("/var/lib/clf/metrics.txt")),
+            Ok(Target::File(String::from("/var/lib/clf/metrics.txt")))
+        );
+        assert!(Target::try_from(String::from("http://localhost")).is_err());
+    }
+
+    #[test]
+    fn test_tcp_output() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut output = Output::new(
+            OutputConfig {
+                format: OutputFormat::Graphite,
+                target: Target::Tcp(listener.local_addr().unwrap().to_string()),
+                prefix: default_prefix(),
+                tags: BTreeMap::new(),
+                queue_size: 1,
+            },
+            "access.log",
+        )
+        .unwrap();
+        let update = UIUpdate {
+            requests: 3,
+            ..Default::default()
+        };
+        output.flush(&update);
+
+        let (stream, _) = listener.accept().unwrap();
+        let line = BufReader::new(stream).lines().next().unwrap().unwrap();
+        assert!(line.starts_with("clf.requests.total;file=access.log 3 "));
+    }
+}
@@ -0,0 +1,156 @@
+use super::{pack, udp_socket, Sink, MAX_PACKET_SIZE};
+use crate::UIUpdate;
+use std::{io, net::UdpSocket};
+
+// Sends the stats of every refresh interval to a DogStatsD (or plain StatsD) agent over UDP
+pub struct StatsD {
+    socket: UdpSocket,
+    prefix: String,
+    // Tags added to every metric, e.g. the log file
+    tags: Vec<String>,
+}
+
+impl StatsD {
+    pub fn new(addr: &str, prefix: &str, filename: &str) -> Result<StatsD, io::Error> {
+        let socket = udp_socket(addr)?;
+        // A missing agent must never stall the stats thread
+        socket.set_nonblocking(true)?;
+        Ok(StatsD {
+            socket,
+            prefix: prefix.trim_end_matches('.').to_owned(),
+            tags: vec![tag("file", filename)],
+        })
+    }
+
+    // Metric lines for one refresh interval
+    fn lines(&self, update: &UIUpdate) -> Vec<String> {
+        let mut lines = vec![
+            self.line("requests.total", update.requests, "c", None, &[]),
+            self.line("response.bytes", update.bytes, "c", None, &[]),
+            self.line("parse_errors", update.parse_errors, "c", None, &[]),
+        ];
+        for ((section, status, method), count) in update.stats_breakdown.iter() {
+            lines.push(self.line(
+                "requests",
+                count,
+                "c",
+                None,
+                &[
+                    tag("section", &format!("/{}", section)),
+                    tag("status", &status.to_string()),
+                    tag("method", method),
+                ],
+            ));
+        }
+        // One value per bucket, the sample rate makes the agent count it as many times as it was seen
+        for (_, bucket) in update.response_sizes.buckets() {
+            lines.push(self.line(
+                "response.size",
+                bucket.mean(),
+                "h",
+                Some(1.0 / bucket.count as f64),
+                &[],
+            ));
+        }
+        for alert in update.alerts.iter() {
+            let tags = [tag("rule", &alert.rule.name)];
+            lines.push(self.line("alert.firing", alert.state.firing as u8, "g", None, &tags));
+            lines.push(self.line("alert.value", alert.state.value, "g", None, &tags));
+        }
+        lines
+    }
+
+    fn line(
+        &self,
+        name: &str,
+        value: impl ToString,
+        kind: &str,
+        rate: Option<f64>,
+        tags: &[String],
+    ) -> String {
+        let mut line = format!("{}.{}:{}|{}", self.prefix, name, value.to_string(), kind);
+        if let Some(rate) = rate {
+            if rate < 1.0 {
+                line.push_str(&format!("|@{}", rate));
+            }
+        }
+        line.push_str("|#");
+        line.push_str(&self.tags.join(","));
+        for tag in tags {
+            line.push(',');
+            line.push_str(tag);
+        }
+        line
+    }
+}
+
+// Characters that have a meaning in the protocol can't be part of a tag
+fn tag(name: &str, value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| match c {
+            ',' | '|' | '#' | '@' | '\n' | ' ' => '_',
+            c => c,
+        })
+        .collect();
+    format!("{}:{}", name, value)
+}
+
+impl Sink for StatsD {
+    fn flush(&mut self, update: &UIUpdate) {
+        // Agents split datagrams on newlines
+        for packet in pack(self.lines(update), MAX_PACKET_SIZE) {
+            let _ = self.socket.send(packet.as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Histogram;
+    use std::time::Duration;
+
+    #[test]
+    fn test_flush() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut statsd = StatsD::new(
+            &listener.local_addr().unwrap().to_string(),
+            "clf.",
+            "/tmp/access.log",
+        )
+        .unwrap();
+
+        let mut update = UIUpdate {
+            requests: 3,
+            bytes: 300,
+            ..Default::default()
+        };
+        update
+            .stats_breakdown
+            .insert((String::from("api"), 503, String::from("GET")), 3);
+        update.response_sizes = Histogram::default();
+        for _ in 0..3 {
+            update.response_sizes.record(100);
+        }
+        statsd.flush(&update);
+
+        let mut buf = [0; MAX_PACKET_SIZE];
+        let size = listener.recv(&mut buf).unwrap();
+        let packet = String::from_utf8_lossy(&buf[..size]);
+        let lines = packet.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "clf.requests.total:3|c|#file:/tmp/access.log",
+                "clf.response.bytes:300|c|#file:/tmp/access.log",
+                "clf.parse_errors:0|c|#file:/tmp/access.log",
+                "clf.requests:3|c|#file:/tmp/access.log,section:/api,status:503,method:GET",
+                "clf.response.size:100|h|@0.3333333333333333|#file:/tmp/access.log",
+            ]
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use super::Sink;
+use crate::{AlertEvent, UIUpdate, UNITS};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rusqlite::{params, Connection};
+use std::{
+    sync::mpsc::{sync_channel, SyncSender},
+    thread,
+};
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+CREATE TABLE IF NOT EXISTS intervals (
+    id INTEGER PRIMARY KEY,
+    start INTEGER NOT NULL,
+    end INTEGER NOT NULL,
+    requests INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    parse_errors INTEGER NOT NULL,
+    avg_rate INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS intervals_end ON intervals (end);
+CREATE TABLE IF NOT EXISTS sections (
+    interval_id INTEGER NOT NULL REFERENCES intervals (id) ON DELETE CASCADE,
+    section TEXT NOT NULL,
+    requests INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sections_interval ON sections (interval_id);
+CREATE TABLE IF NOT EXISTS statuses (
+    interval_id INTEGER NOT NULL REFERENCES intervals (id) ON DELETE CASCADE,
+    status INTEGER NOT NULL,
+    requests INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS statuses_interval ON statuses (interval_id);
+CREATE TABLE IF NOT EXISTS addresses (
+    interval_id INTEGER NOT NULL REFERENCES intervals (id) ON DELETE CASCADE,
+    address TEXT NOT NULL,
+    requests INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS addresses_interval ON addresses (interval_id);
+CREATE TABLE IF NOT EXISTS alert_events (
+    id INTEGER PRIMARY KEY,
+    time INTEGER NOT NULL,
+    rule TEXT NOT NULL,
+    firing INTEGER NOT NULL,
+    value REAL NOT NULL,
+    threshold REAL NOT NULL,
+    unit TEXT NOT NULL,
+    window INTEGER NOT NULL,
+    key TEXT,
+    duration INTEGER
+);
+CREATE INDEX IF NOT EXISTS alert_events_time ON alert_events (time);
+";
+
+// Aggregates of a single refresh interval as they are stored
+struct Interval {
+    start: i64,
+    end: i64,
+    requests: u64,
+    bytes: u64,
+    parse_errors: u64,
+    avg_rate: u64,
+    sections: Vec<(String, u64)>,
+    statuses: Vec<(u16, u64)>,
+    // Only the busiest addresses, there are way too many of them to keep them all
+    addresses: Vec<(String, u64)>,
+}
+
+enum Record {
+    Interval(Interval),
+    Alert(AlertEvent),
+}
+
+// Units are static strings everywhere else, anything unknown comes back empty
+fn unit(unit: &str) -> &'static str {
+    UNITS
+        .iter()
+        .find(|known| **known == unit)
+        .copied()
+        .unwrap_or("")
+}
+
+// SQLite database holding the aggregates of every refresh interval and alert transitions
+pub struct Database {
+    connection: Connection,
+    // Rows older than this are deleted
+    retention: Duration,
+    // Amount of addresses stored per interval
+    top_ips: usize,
+}
+
+impl Database {
+    pub fn open(path: &str, retention: Duration, top_ips: usize) -> Result<Database, String> {
+        let connection = Connection::open(path)
+            .and_then(|connection| connection.execute_batch(SCHEMA).map(|_| connection))
+            .map_err(|err| format!("Could not open database {}: {}", path, err))?;
+        Ok(Database {
+            connection,
+            retention,
+            top_ips,
+        })
+    }
+
+    // Latest alert transitions, oldest first
+    pub fn alert_history(&self, limit: usize) -> Result<Vec<AlertEvent>, String> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT time, rule, firing, value, threshold, unit, window, key, duration
+                 FROM alert_events ORDER BY time DESC, id DESC LIMIT ?",
+            )
+            .map_err(|err| err.to_string())?;
+        let events = statement
+            .query_map(params![limit as i64], |row| {
+                Ok(AlertEvent {
+                    time: Utc.timestamp(row.get(0)?, 0),
+                    name: row.get(1)?,
+                    firing: row.get(2)?,
+                    value: row.get(3)?,
+                    threshold: row.get(4)?,
+                    unit: unit(&row.get::<_, String>(5)?),
+                    window: row.get::<_, i64>(6)? as u64,
+                    key: row.get(7)?,
+                    duration: row.get::<_, Option<i64>>(8)?.map(Duration::seconds),
+                })
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| err.to_string())?;
+        Ok(events.into_iter().rev().collect())
+    }
+
+    fn insert_interval(&mut self, interval: &Interval) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO intervals (start, end, requests, bytes, parse_errors, avg_rate)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                interval.start,
+                interval.end,
+                interval.requests as i64,
+                interval.bytes as i64,
+                interval.parse_errors as i64,
+                interval.avg_rate as i64
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+        for (section, requests) in interval.sections.iter() {
+            transaction.execute(
+                "INSERT INTO sections (interval_id, section, requests) VALUES (?, ?, ?)",
+                params![id, section, *requests as i64],
+            )?;
+        }
+        for (status, requests) in interval.statuses.iter() {
+            transaction.execute(
+                "INSERT INTO statuses (interval_id, status, requests) VALUES (?, ?, ?)",
+                params![id, status, *requests as i64],
+            )?;
+        }
+        for (address, requests) in interval.addresses.iter() {
+            transaction.execute(
+                "INSERT INTO addresses (interval_id, address, requests) VALUES (?, ?, ?)",
+                params![id, address, *requests as i64],
+            )?;
+        }
+        // A retention reaching back before the earliest date keeps everything
+        let cutoff = Utc
+            .timestamp(interval.end, 0)
+            .checked_sub_signed(self.retention);
+        if let Some(cutoff) = cutoff.map(|cutoff| cutoff.timestamp()) {
+            transaction.execute("DELETE FROM intervals WHERE end < ?", params![cutoff])?;
+            transaction.execute("DELETE FROM alert_events WHERE time < ?", params![cutoff])?;
+        }
+        transaction.commit()
+    }
+
+    fn insert_alert(&self, event: &AlertEvent) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "INSERT INTO alert_events (time, rule, firing, value, threshold, unit, window, key, duration)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                event.time.timestamp(),
+                event.name,
+                event.firing,
+                event.value,
+                event.threshold,
+                event.unit,
+                event.window as i64,
+                event.key,
+                event.duration.map(|duration| duration.num_seconds())
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn write(&mut self, record: &Record) -> rusqlite::Result<()> {
+        match record {
+            Record::Interval(interval) => self.insert_interval(interval),
+            Record::Alert(event) => self.insert_alert(event),
+        }
+    }
+}
+
+// Hands the aggregates over to a thread writing them to the database, so a slow disk can't stall the stats
+pub struct Storage {
+    top_ips: usize,
+    // End of the previous interval, start of the next one
+    start: DateTime<Utc>,
+    tx_records: SyncSender<Record>,
+}
+
+// Records waiting to be written, new ones are dropped while it's full
+const QUEUE_SIZE: usize = 100;
+
+impl Storage {
+    pub fn new(mut database: Database) -> Storage {
+        let top_ips = database.top_ips;
+        let (tx_records, rx_records) = sync_channel::<Record>(QUEUE_SIZE);
+        thread::spawn(move || {
+            for record in rx_records {
+                // There's nobody to report the failure to, the record is lost
+                let _ = database.write(&record);
+            }
+        });
+        Storage {
+            top_ips,
+            start: Utc::now(),
+            tx_records,
+        }
+    }
+}
+
+impl Sink for Storage {
+    fn flush(&mut self, update: &UIUpdate) {
+        let end = Utc::now();
+        let mut addresses = update
+            .stats_addresses
+            .iter()
+            .map(|(address, requests)| (address.to_string(), *requests))
+            .collect::<Vec<_>>();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        addresses.truncate(self.top_ips);
+        let interval = Interval {
+            start: self.start.timestamp(),
+            end: end.timestamp(),
+            requests: update.requests,
+            bytes: update.bytes,
+            parse_errors: update.parse_errors,
+            avg_rate: update.avg_rate,
+            sections: update
+                .stats_endpoints
+                .iter()
+                .map(|(section, requests)| (format!("/{}", section), *requests))
+                .collect(),
+            statuses: update
+                .stats_statuses
+                .iter()
+                .map(|(status, requests)| (*status, *requests))
+                .collect(),
+            addresses,
+        };
+        self.start = end;
+        let _ = self.tx_records.try_send(Record::Interval(interval));
+    }
+
+    fn alert(&mut self, event: &AlertEvent) {
+        let _ = self.tx_records.try_send(Record::Alert(event.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(end: i64, requests: u64) -> Interval {
+        Interval {
+            start: end - 10,
+            end,
+            requests,
+            bytes: requests * 100,
+            parse_errors: 0,
+            avg_rate: requests / 10,
+            sections: vec![(String::from("/api"), requests)],
+            statuses: vec![(200, requests)],
+            addresses: vec![(String::from("10.0.0.1"), requests)],
+        }
+    }
+
+    fn event(time: i64, firing: bool) -> AlertEvent {
+        event_in(time, firing, "req/s")
+    }
+
+    fn event_in(time: i64, firing: bool, unit: &'static str) -> AlertEvent {
+        AlertEvent {
+            name: String::from("High traffic"),
+            firing,
+            time: Utc.timestamp(time, 0),
+            value: 12.5,
+            threshold: 10.0,
+            unit,
+            window: 120,
+            key: None,
+            duration: if firing {
+                None
+            } else {
+                Some(Duration::seconds(60))
+            },
+        }
+    }
+
+    fn count(database: &Database, table: &str) -> i64 {
+        database
+            .connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}", table),
+                params![],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_database() {
+        let mut database = Database::open(":memory:", Duration::hours(1), 10).unwrap();
+        let now = 1_600_000_000;
+        database
+            .write(&Record::Alert(event(now - 7200, true)))
+            .unwrap();
+        database
+            .write(&Record::Alert(event(now - 60, true)))
+            .unwrap();
+        database.write(&Record::Alert(event(now, false))).unwrap();
+        database
+            .write(&Record::Interval(interval(now - 7200, 5)))
+            .unwrap();
+        assert_eq!(count(&database, "intervals"), 1);
+        database
+            .write(&Record::Interval(interval(now, 30)))
+            .unwrap();
+
+        // Everything older than an hour is gone, together with the rows of the interval
+        assert_eq!(count(&database, "intervals"), 1);
+        assert_eq!(count(&database, "sections"), 1);
+        assert_eq!(count(&database, "addresses"), 1);
+        let requests: i64 = database
+            .connection
+            .query_row("SELECT requests FROM statuses", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(requests, 30);
+
+        assert_eq!(
+            database.alert_history(10).unwrap(),
+            vec![event(now - 60, true), event(now, false)]
+        );
+        assert_eq!(database.alert_history(1).unwrap(), vec![event(now, false)]);
+
+        // Sum rules are in plain requests and bytes
+        for unit in UNITS.iter() {
+            database
+                .write(&Record::Alert(event_in(now + 1, true, unit)))
+                .unwrap();
+            assert_eq!(
+                database.alert_history(1).unwrap(),
+                vec![event_in(now + 1, true, unit)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_database_unbounded_retention() {
+        let mut database = Database::open(":memory:", Duration::days(100_000_000), 10).unwrap();
+        database.write(&Record::Interval(interval(0, 5))).unwrap();
+        database
+            .write(&Record::Interval(interval(1_600_000_000, 30)))
+            .unwrap();
+        assert_eq!(count(&database, "intervals"), 2);
+    }
+}
@@ -0,0 +1,187 @@
+use super::Sink;
+use crate::AlertEvent;
+use chrono::SecondsFormat;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    sync::mpsc::{sync_channel, SyncSender},
+    thread,
+    time::Duration,
+};
+
+// An endpoint that receives a JSON payload on every alert transition:
+//
+// [[webhooks]]
+// url = "http://localhost:8080/alerts"
+// timeout = 5
+// retries = 3
+// queue_size = 100
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    // Seconds to wait for the endpoint to respond
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    // Attempts after the first failed one, with exponentially growing pauses in between
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    // Events waiting to be delivered, new events are dropped while it's full
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_queue_size() -> usize {
+    100
+}
+
+// Pause before the first retry, doubled with every next one
+const FIRST_RETRY_PAUSE: Duration = Duration::from_secs(1);
+
+pub struct Webhook {
+    tx_events: SyncSender<Value>,
+}
+
+impl Webhook {
+    // Spawn a thread delivering events one by one, so a slow endpoint can't stall the stats
+    pub fn new(config: WebhookConfig) -> Webhook {
+        let (tx_events, rx_events) = sync_channel(config.queue_size);
+        thread::spawn(move || {
+            let agent = ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(config.timeout))
+                .build();
+            for payload in rx_events {
+                deliver(&agent, &config, &payload);
+            }
+        });
+        Webhook { tx_events }
+    }
+}
+
+pub fn event_payload(event: &AlertEvent) -> Value {
+    json!({
+        "rule": event.name,
+        "state": if event.firing { "firing" } else { "resolved" },
+        "value": event.value,
+        "threshold": event.threshold,
+        "unit": event.unit,
+        "window": event.window,
+        "key": event.key,
+        "timestamp": event.time.to_rfc3339_opts(SecondsFormat::Secs, true),
+    })
+}
+
+// There's nobody to report the failure to, so the event is given up after the last retry
+fn deliver(agent: &ureq::Agent, config: &WebhookConfig, payload: &Value) -> bool {
+    let mut pause = FIRST_RETRY_PAUSE;
+    for attempt in 0..=config.retries {
+        if attempt > 0 {
+            thread::sleep(pause);
+            pause *= 2;
+        }
+        match agent.post(&config.url).send_json(payload.clone()) {
+            Ok(_) => return true,
+            // Endpoint doesn't like the request, sending it again won't help
+            Err(ureq::Error::Status(code, _)) if code < 500 => return false,
+            Err(_) => continue,
+        }
+    }
+    false
+}
+
+impl Sink for Webhook {
+    fn alert(&mut self, event: &AlertEvent) {
+        // Never block the stats thread, full queue means the endpoint can't keep up anyway
+        let _ = self.tx_events.try_send(event_payload(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc::channel,
+    };
+
+    // Read a single HTTP request from the stub server and return its body
+    fn read_body(reader: &mut BufReader<std::net::TcpStream>) -> String {
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_webhook_retries_until_delivered() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+        let (tx_bodies, rx_bodies) = channel();
+        // Stub that fails the first request and accepts the second one
+        thread::spawn(move || {
+            for (index, stream) in listener.incoming().take(2).enumerate() {
+                let stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                tx_bodies.send(read_body(&mut reader)).unwrap();
+                let status = if index == 0 {
+                    "500 Internal Server Error"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    &stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+
+        let mut webhook = Webhook::new(WebhookConfig {
+            url,
+            timeout: 1,
+            retries: 2,
+            queue_size: 1,
+        });
+        webhook.alert(&AlertEvent {
+            name: String::from("High traffic"),
+            firing: true,
+            time: Utc::now(),
+            value: 12.5,
+            threshold: 10.0,
+            unit: "req/s",
+            window: 120,
+            key: None,
+            duration: None,
+        });
+        for _ in 0..2 {
+            let body: Value =
+                serde_json::from_str(&rx_bodies.recv_timeout(Duration::from_secs(5)).unwrap())
+                    .unwrap();
+            assert_eq!(body["rule"], "High traffic");
+            assert_eq!(body["state"], "firing");
+            assert_eq!(body["value"], 12.5);
+            assert_eq!(body["window"], 120);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::UIUpdate;
+use std::collections::VecDeque;
+
+// Amount of refresh intervals shown by the rate chart
+pub const MAX_RATE_POINTS: usize = 120;
+
+// Request rate of one refresh interval and whether any alert was firing at its end
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RatePoint {
+    rate: f64,
+    firing: bool,
+}
+
+// Rates of the last refresh intervals, the oldest first
+pub struct RateHistory {
+    points: VecDeque<RatePoint>,
+    refresh_interval: u64,
+    // Thresholds of the rules measured in the same req/s as the chart
+    thresholds: Vec<(String, f64)>,
+}
+
+impl RateHistory {
+    pub fn new(refresh_interval: u64) -> Self {
+        RateHistory {
+            points: VecDeque::new(),
+            refresh_interval,
+            thresholds: vec![],
+        }
+    }
+
+    // Rates of intervals of a different length don't fit on the same X axis, start over
+    pub fn set_refresh_interval(&mut self, refresh_interval: u64) {
+        if refresh_interval != self.refresh_interval {
+            self.points.clear();
+            self.refresh_interval = refresh_interval;
+        }
+    }
+
+    pub fn push(&mut self, update: &UIUpdate) {
+        // The placeholder shown before the first interval doesn't know how long it is
+        let refresh_interval = match update.refresh_interval {
+            0 => self.refresh_interval,
+            refresh_interval => refresh_interval,
+        };
+        self.points.push_back(RatePoint {
+            rate: update.requests as f64 / refresh_interval as f64,
+            firing: update.alerts.iter().any(|alert| alert.state.firing),
+        });
+        if self.points.len() > MAX_RATE_POINTS {
+            self.points.pop_front();
+        }
+        self.thresholds = update
+            .alerts
+            .iter()
+            // Rules that watch the overall hit rate can be drawn on top of it
+            .filter(|alert| alert.rule.is_total_rate())
+            .map(|alert| (alert.rule.name.clone(), alert.rule.threshold))
+            .collect();
+    }
+
+    // Seconds before the latest interval covered by the chart, as the left bound of the X axis
+    pub fn span(&self) -> f64 {
+        ((MAX_RATE_POINTS - 1) as u64 * self.refresh_interval) as f64
+    }
+
+    // Upper bound of the Y axis, leaves some room above the highest rate and threshold
+    pub fn max(&self) -> f64 {
+        let max = self
+            .points
+            .iter()
+            .map(|point| point.rate)
+            .chain(self.thresholds.iter().map(|(_, threshold)| *threshold))
+            .fold(0.0, f64::max);
+        (max * 1.2).max(1.0)
+    }
+
+    // (seconds relative to the latest interval, req/s) of every interval
+    pub fn rates(&self) -> Vec<(f64, f64)> {
+        let last = self.points.len() as f64 - 1.0;
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                (
+                    (index as f64 - last) * self.refresh_interval as f64,
+                    point.rate,
+                )
+            })
+            .collect()
+    }
+
+    // Horizontal line across the whole chart for every rule on the same scale
+    pub fn thresholds(&self) -> Vec<(String, [(f64, f64); 2])> {
+        self.thresholds
+            .iter()
+            .map(|(name, threshold)| {
+                (
+                    name.clone(),
+                    [(-self.span(), *threshold), (0.0, *threshold)],
+                )
+            })
+            .collect()
+    }
+
+    // Vertical line at the interval that came `newer` intervals before the latest one, None if
+    // it's not on the chart anymore
+    pub fn marker(&self, newer: usize) -> Option<[(f64, f64); 2]> {
+        if newer >= self.points.len() {
+            return None;
+        }
+        let x = -((newer as u64 * self.refresh_interval) as f64);
+        Some([(x, 0.0), (x, self.max())])
+    }
+
+    // Points filling every interval an alert was firing in, one per cell of a chart that is
+    // that many cells wide and high
+    pub fn shading(&self, width: u16, height: u16) -> Vec<(f64, f64)> {
+        let (width, height) = (width.max(1) as f64, height.max(1) as f64);
+        let (x_step, y_step) = (self.span() / width, self.max() / height);
+        let mut points = vec![];
+        for (x, _) in self
+            .rates()
+            .into_iter()
+            .zip(self.points.iter())
+            .filter(|(_, point)| point.firing)
+            .map(|(rate, _)| rate)
+        {
+            // The interval ends at its point on the chart
+            let mut cell_x = (x - self.refresh_interval as f64).max(-self.span());
+            while cell_x <= x {
+                let mut cell_y = 0.0;
+                while cell_y <= self.max() {
+                    points.push((cell_x, cell_y));
+                    cell_y += y_step;
+                }
+                cell_x += x_step;
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertRule, AlertState, AlertStatus, GroupBy};
+
+    fn update(requests: u64, firing: bool) -> UIUpdate {
+        let mut grouped = AlertRule::total_traffic(20, 5);
+        grouped.group_by = Some(GroupBy::Ip);
+        let mut update = UIUpdate {
+            requests,
+            ..Default::default()
+        };
+        update.alerts = vec![
+            AlertStatus {
+                rule: AlertRule::total_traffic(20, 10),
+                state: AlertState {
+                    firing,
+                    ..Default::default()
+                },
+            },
+            AlertStatus {
+                rule: grouped,
+                state: Default::default(),
+            },
+        ];
+        update
+    }
+
+    #[test]
+    fn test_rates() {
+        let mut history = RateHistory::new(10);
+        for requests in 0..MAX_RATE_POINTS as u64 + 2 {
+            history.push(&update(requests * 10, false));
+        }
+        let rates = history.rates();
+        assert_eq!(rates.len(), MAX_RATE_POINTS);
+        assert_eq!(rates[0], (-history.span(), 2.0));
+        assert_eq!(rates[MAX_RATE_POINTS - 1], (0.0, 121.0));
+        assert_eq!(history.max(), 121.0 * 1.2);
+        assert_eq!(history.marker(0), Some([(0.0, 0.0), (0.0, history.max())]));
+        assert_eq!(history.marker(3).unwrap()[0], (-30.0, 0.0));
+        assert_eq!(history.marker(MAX_RATE_POINTS), None);
+        // Rates are over the interval the update was collected in
+        let mut short = update(30, false);
+        short.refresh_interval = 5;
+        history.push(&short);
+        assert_eq!(history.rates()[MAX_RATE_POINTS - 1], (0.0, 6.0));
+        // Grouped rules are on a different scale
+        assert_eq!(
+            history.thresholds(),
+            vec![(
+                String::from("High traffic"),
+                [(-history.span(), 10.0), (0.0, 10.0)]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_shading() {
+        let mut history = RateHistory::new(10);
+        history.push(&update(50, false));
+        assert!(history.shading(100, 10).is_empty());
+        history.push(&update(150, true));
+        assert_eq!(history.max(), 18.0);
+        let shading = history.shading(119, 9);
+        // Ten seconds are one cell wide, ten cells high to reach the top
+        assert_eq!(shading.len(), 2 * 10);
+        assert!(shading
+            .iter()
+            .all(|(x, y)| (-10.0..=0.0).contains(x) && (0.0..=18.0).contains(y)));
+    }
+}
@@ -0,0 +1,185 @@
+use super::{sorted_hits, Rows, Theme, UIUpdate};
+use crate::{Breakdown, MAX_BREAKDOWNS};
+use std::{collections::HashMap, net::IpAddr, vec::IntoIter};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Row, Table, Wrap},
+    Frame,
+};
+
+// What the drill-down view is opened for
+#[derive(Clone, Debug, PartialEq)]
+pub enum Detail {
+    Section(String),
+    Address(IpAddr),
+}
+
+impl Detail {
+    fn breakdown<'a>(&self, ui_update: &'a UIUpdate) -> Option<&'a Breakdown> {
+        match self {
+            Detail::Section(section) => ui_update.section_breakdowns.get(section),
+            Detail::Address(address) => ui_update.address_breakdowns.get(address),
+        }
+    }
+
+    fn title(&self) -> String {
+        match self {
+            Detail::Section(section) => format!("Section /{}/*", section),
+            Detail::Address(address) => format!("Address {}", address),
+        }
+    }
+}
+
+fn create_block(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(Span::styled(
+        title,
+        Style::default().add_modifier(Modifier::BOLD),
+    ))
+}
+
+// Table with the keys sorted by their hits
+fn hits_table<'a, K: Ord>(
+    title: &'a str,
+    header: &'a str,
+    hits: &HashMap<K, u64>,
+    format: impl Fn(&K) -> String,
+    style: Style,
+) -> Table<'a, IntoIter<&'a str>, IntoIter<Row<IntoIter<String>>>> {
+    let rows: Rows = sorted_hits(hits)
+        .into_iter()
+        .map(|(key, hits)| Row::StyledData(vec![format(key), hits.to_string()].into_iter(), style))
+        .collect();
+    Table::new(vec![header, "Hits"].into_iter(), rows.into_iter())
+        .block(create_block(title))
+        .widths(&[Constraint::Percentage(80), Constraint::Percentage(20)])
+        .column_spacing(1)
+}
+
+// Everything known about the selected section or address in the last refresh interval
+pub fn render<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    detail: &Detail,
+    ui_update: &UIUpdate,
+    theme: &Theme,
+) {
+    let block = Block::default().borders(Borders::ALL).title(Span::styled(
+        format!("{} (Esc to close)", detail.title()),
+        theme.accent.add_modifier(Modifier::BOLD),
+    ));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let breakdown = match detail.breakdown(ui_update) {
+        Some(breakdown) => breakdown,
+        None => {
+            f.render_widget(
+                Paragraph::new(format!(
+                    "No requests in the last refresh interval, or not among the {} busiest",
+                    MAX_BREAKDOWNS
+                )),
+                inner,
+            );
+            return;
+        }
+    };
+
+    let key_style = theme.key;
+    let value_style = theme.value;
+    let error_style = if breakdown.error_rate() > 0.0 {
+        theme.alert
+    } else {
+        value_style
+    };
+    let methods = sorted_hits(&breakdown.methods)
+        .into_iter()
+        .map(|(method, hits)| format!("{} {}", method, hits))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let summary = Paragraph::new(vec![
+        Spans::from(vec![
+            Span::styled("Requests: ", key_style),
+            Span::styled(breakdown.requests.to_string(), value_style),
+        ]),
+        Spans::from(vec![
+            Span::styled("Bytes: ", key_style),
+            Span::styled(breakdown.bytes.to_string(), value_style),
+        ]),
+        Spans::from(vec![
+            Span::styled("Error rate: ", key_style),
+            Span::styled(format!("{:.1}%", breakdown.error_rate()), error_style),
+        ]),
+        Spans::from(vec![
+            Span::styled("Methods: ", key_style),
+            Span::styled(methods, value_style),
+        ]),
+    ])
+    .block(create_block("Summary"))
+    .wrap(Wrap { trim: true });
+
+    let paths = hits_table(
+        "Paths",
+        "Path",
+        &breakdown.paths,
+        String::clone,
+        value_style,
+    );
+    let statuses = hits_table(
+        "Status codes",
+        "Code",
+        &breakdown.statuses,
+        u16::to_string,
+        value_style,
+    );
+    // A section is about who hits it, an address is about what it hits
+    let related = match detail {
+        Detail::Section(_) => hits_table(
+            "Top addresses",
+            "Address",
+            &breakdown.addresses,
+            IpAddr::to_string,
+            value_style,
+        ),
+        Detail::Address(_) => hits_table(
+            "Sections",
+            "Section",
+            &breakdown.sections,
+            |section| format!("/{}/*", section),
+            value_style,
+        ),
+    };
+    let log_samples = Paragraph::new(
+        breakdown
+            .log_samples
+            .iter()
+            .rev()
+            .map(|log| Spans::from(Span::raw(&**log)))
+            .collect::<Vec<_>>(),
+    )
+    .block(create_block("Recent log lines"))
+    .wrap(Wrap { trim: true });
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(35),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+            ]
+            .as_ref(),
+        )
+        .split(rows[0]);
+    f.render_widget(summary, columns[0]);
+    f.render_widget(paths, columns[1]);
+    f.render_widget(related, columns[2]);
+    f.render_widget(statuses, columns[3]);
+    f.render_widget(log_samples, rows[1]);
+}
@@ -0,0 +1,52 @@
+use super::{popup, Theme};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, Clear, Row, Table},
+    Frame,
+};
+
+// Every key binding, the footer only has room for the most used ones
+const BINDINGS: &[(&str, &str)] = &[
+    ("q, Ctrl-C", "Quit"),
+    ("?", "Show this help, any key closes it"),
+    ("Tab / Shift-Tab", "Move focus to the next / previous panel"),
+    ("\u{2191}/\u{2193}, k / j", "Select the previous / next row"),
+    ("PgUp / PgDn", "Move the selection by a screen of rows"),
+    ("Home / End", "Select the first / last row"),
+    ("Enter", "Open the details of the selected section or IP"),
+    ("Esc / Enter / Backspace", "Close the details"),
+    (
+        "/",
+        "Filter what is shown, an empty filter shows everything",
+    ),
+    ("p", "Pause / resume the display"),
+    (
+        "\u{2190}/\u{2192}",
+        "Step back / forward through the past refresh intervals",
+    ),
+    (
+        "s",
+        "Change the refresh interval, the alert rule and the HTTP codes",
+    ),
+];
+
+pub fn render<B: Backend>(f: &mut Frame<B>, area: Rect, theme: &Theme) {
+    let area = popup(area, 90, BINDINGS.len() as u16 + 4);
+    let rows = BINDINGS
+        .iter()
+        .map(|(key, action)| Row::Data(vec![*key, *action].into_iter()));
+    let table = Table::new(vec!["Key", "Action"].into_iter(), rows)
+        .block(Block::default().borders(Borders::ALL).title(Span::styled(
+            "Key bindings (any key to close)",
+            theme.accent.add_modifier(Modifier::BOLD),
+        )))
+        .header_style(Style::default().add_modifier(Modifier::BOLD))
+        .style(theme.value)
+        .widths(&[Constraint::Length(24), Constraint::Min(0)])
+        .column_spacing(1);
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
@@ -0,0 +1,217 @@
+use super::navigation::Panel;
+use serde::Deserialize;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+
+// Which panels appear and how much room they get, the [layout] section of the config file.
+// Panels of a column share its height in proportion to their sizes, a size of 0 hides the panel
+//
+// [layout]
+// chart = 0
+// left = 30
+// history = 0
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    // Height of the rate chart in rows
+    pub chart: u16,
+    // Width of the general stats next to the alerts, in percent
+    pub stats: u16,
+    // Width of the column with the endpoints and addresses, in percent
+    pub left: u16,
+    pub endpoints: u16,
+    pub addresses: u16,
+    // The right column
+    pub http_codes: u16,
+    pub history: u16,
+    pub log_samples: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            chart: 10,
+            stats: 40,
+            left: 20,
+            endpoints: 50,
+            addresses: 50,
+            http_codes: 50,
+            history: 20,
+            log_samples: 30,
+        }
+    }
+}
+
+// Where everything goes, None for the hidden panels
+#[derive(Debug, PartialEq)]
+pub struct Areas {
+    pub alerts: Rect,
+    pub stats: Option<Rect>,
+    pub chart: Option<Rect>,
+    // Everything below the chart, the drill-down view takes all of it
+    pub main: Rect,
+    pub footer: Rect,
+    pub endpoints: Option<Rect>,
+    pub addresses: Option<Rect>,
+    pub http_codes: Option<Rect>,
+    pub history: Option<Rect>,
+    pub log_samples: Option<Rect>,
+}
+
+// Split the area between the panels of non-zero size, in proportion to the sizes
+fn share(area: Rect, direction: Direction, sizes: &[u16]) -> Vec<Option<Rect>> {
+    let total = sizes.iter().map(|size| *size as u32).sum::<u32>();
+    if total == 0 {
+        return vec![None; sizes.len()];
+    }
+    let visible = sizes.iter().filter(|size| **size > 0);
+    let mut chunks = Layout::default()
+        .direction(direction)
+        .constraints(
+            visible
+                .map(|size| Constraint::Ratio(*size as u32, total))
+                .collect::<Vec<_>>(),
+        )
+        .split(area)
+        .into_iter();
+    sizes
+        .iter()
+        .map(|size| if *size > 0 { chunks.next() } else { None })
+        .collect()
+}
+
+impl LayoutConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.stats > 100 || self.left > 100 {
+            return Err(String::from("layout widths are percentages, 100 at most"));
+        }
+        Ok(())
+    }
+
+    // A column without any visible panel leaves all the room to the other one
+    fn left_width(&self) -> u16 {
+        match (
+            self.endpoints > 0 || self.addresses > 0,
+            self.http_codes > 0 || self.history > 0 || self.log_samples > 0,
+        ) {
+            (false, _) => 0,
+            (true, false) => 100,
+            (true, true) => self.left,
+        }
+    }
+
+    // Whether the panel is on the screen, hidden panels can't take focus
+    pub fn shows(&self, panel: Panel) -> bool {
+        let (left, right) = (self.left_width() > 0, self.left_width() < 100);
+        match panel {
+            Panel::Endpoints => left && self.endpoints > 0,
+            Panel::Addresses => left && self.addresses > 0,
+            Panel::HttpCode(_) => right && self.http_codes > 0,
+            Panel::History => right && self.history > 0,
+        }
+    }
+
+    pub fn split(&self, area: Rect, alerts_height: u16) -> Areas {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(alerts_height),
+                    Constraint::Length(self.chart),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let top = share(
+            rows[0],
+            Direction::Horizontal,
+            &[100 - self.stats.min(99), self.stats],
+        );
+
+        let left_sizes = [self.endpoints, self.addresses];
+        let right_sizes = [self.http_codes, self.history, self.log_samples];
+        let left_width = self.left_width();
+        let columns = share(
+            rows[2],
+            Direction::Horizontal,
+            &[left_width, 100 - left_width],
+        );
+        let left = match columns[0] {
+            Some(column) => share(column, Direction::Vertical, &left_sizes),
+            None => vec![None; left_sizes.len()],
+        };
+        let right = match columns[1] {
+            Some(column) => share(column, Direction::Vertical, &right_sizes),
+            None => vec![None; right_sizes.len()],
+        };
+
+        Areas {
+            alerts: top[0].unwrap_or(rows[0]),
+            stats: top[1],
+            chart: Some(rows[1]).filter(|_| self.chart > 0),
+            main: rows[2],
+            footer: rows[3],
+            endpoints: left[0],
+            addresses: left[1],
+            http_codes: right[0],
+            history: right[1],
+            log_samples: right[2],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let layout = LayoutConfig::default();
+        let areas = layout.split(Rect::new(0, 0, 102, 52), 4);
+        assert_eq!(areas.alerts, Rect::new(1, 1, 60, 4));
+        assert_eq!(areas.stats, Some(Rect::new(61, 1, 40, 4)));
+        assert_eq!(areas.chart, Some(Rect::new(1, 5, 100, 10)));
+        assert_eq!(areas.main, Rect::new(1, 15, 100, 35));
+        assert_eq!(areas.footer, Rect::new(1, 50, 100, 1));
+        assert_eq!(areas.endpoints.unwrap().width, 20);
+        assert_eq!(areas.http_codes.unwrap().x, 21);
+        assert!(areas.log_samples.is_some());
+        assert!(layout.shows(Panel::History));
+    }
+
+    #[test]
+    fn test_hidden_panels() {
+        let layout: LayoutConfig = toml::from_str(
+            r#"
+            chart = 0
+            stats = 0
+            endpoints = 0
+            addresses = 0
+            history = 0
+            "#,
+        )
+        .unwrap();
+        let areas = layout.split(Rect::new(0, 0, 102, 52), 4);
+        assert_eq!(areas.alerts.width, 100);
+        assert_eq!(areas.stats, None);
+        assert_eq!(areas.chart, None);
+        assert_eq!(areas.main, Rect::new(1, 5, 100, 45));
+        assert_eq!(areas.endpoints, None);
+        assert_eq!(areas.history, None);
+        // The right column takes the whole width and the remaining panels its whole height
+        assert_eq!(areas.http_codes, Some(Rect::new(1, 5, 100, 28)));
+        assert_eq!(areas.log_samples, Some(Rect::new(1, 33, 100, 17)));
+        assert!(!layout.shows(Panel::Endpoints));
+        assert!(!layout.shows(Panel::History));
+        assert!(layout.shows(Panel::HttpCode(404)));
+
+        assert!(toml::from_str::<LayoutConfig>("graph = 5").is_err());
+        let layout = LayoutConfig {
+            left: 120,
+            ..Default::default()
+        };
+        assert!(layout.validate().is_err());
+    }
+}
@@ -0,0 +1,761 @@
+mod chart;
+mod detail;
+mod help;
+mod layout;
+mod navigation;
+mod prompt;
+mod settings;
+mod theme;
+mod timeline;
+
+use crate::{format_duration, AlertEvent, AlertStatus, Breakdown, Control, Filter, Histogram};
+use chart::RateHistory;
+use chrono::{DateTime, Duration, Local};
+use detail::Detail;
+pub use layout::LayoutConfig;
+use navigation::{Navigation, Panel};
+use prompt::{Outcome, Prompt};
+use settings::{Action, Settings};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::IpAddr,
+    sync::mpsc::{Receiver, Sender},
+    vec::IntoIter,
+};
+use termion::{
+    event::Key,
+    raw::{IntoRawMode, RawTerminal},
+    screen::AlternateScreen,
+};
+pub use theme::{Theme, ThemeConfig};
+use timeline::Timeline;
+use tui::{
+    backend::{Backend, TermionBackend},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    symbols::Marker,
+    text::{Span, Spans},
+    widgets::{
+        Axis, Block, Borders, Chart, Dataset, GraphType, List, ListItem, Paragraph, Row, Table,
+        Wrap,
+    },
+    Terminal,
+};
+
+pub enum RenderMessage {
+    // Boxed as it dwarfs every other message
+    UI(Box<UIUpdate>),
+    Key(Key),
+    // Ctrl-C or a termination signal, "q" only quits outside of text input so it's handled by the UI
+    Exit,
+    // Error of a worker thread the application can't go on without, it's printed once the
+    // terminal is restored
+    Fatal(String),
+}
+
+// Amount of alert transitions kept for the history panel
+pub const MAX_ALERT_HISTORY: usize = 1000;
+
+pub struct UIUpdate {
+    pub stats_endpoints: HashMap<String, u64>,
+    pub stats_addresses: HashMap<IpAddr, u64>,
+    pub stats_http_codes: HashMap<u16, HashMap<String, u64>>,
+    pub avg_rate: u64,
+    pub log_samples: Vec<String>,
+    pub alerts: Vec<AlertStatus>,
+    // Alert transitions that happened during the last refresh interval
+    pub alert_events: Vec<AlertEvent>,
+    pub stats_statuses: HashMap<u16, u64>,
+    pub stats_methods: HashMap<String, u64>,
+    // Requests by section, response code and method
+    pub stats_breakdown: HashMap<(String, u16, String), u64>,
+    pub response_sizes: Histogram,
+    // Details of every section and client address
+    pub section_breakdowns: HashMap<String, Breakdown>,
+    pub address_breakdowns: HashMap<IpAddr, Breakdown>,
+    // Totals of the last refresh interval
+    pub requests: u64,
+    pub bytes: u64,
+    pub parse_errors: u64,
+    // Length of the interval in seconds, it can be changed from the settings dialog
+    pub refresh_interval: u64,
+}
+
+type DynTables<'a> = Vec<Table<'a, IntoIter<String>, IntoIter<Row<IntoIter<String>>>>>;
+type Rows = Vec<Row<IntoIter<String>>>;
+impl Default for UIUpdate {
+    fn default() -> Self {
+        UIUpdate {
+            stats_endpoints: HashMap::new(),
+            stats_addresses: HashMap::new(),
+            stats_http_codes: HashMap::new(),
+            avg_rate: 0,
+            log_samples: vec![
+                String::from("Waiting for data from file-reading thread"),
+                String::from("Waiting for data from file-reading thread"),
+                String::from("Waiting for data from file-reading thread"),
+            ],
+            alerts: vec![],
+            alert_events: vec![],
+            stats_statuses: HashMap::new(),
+            stats_methods: HashMap::new(),
+            stats_breakdown: HashMap::new(),
+            response_sizes: Histogram::default(),
+            section_breakdowns: HashMap::new(),
+            address_breakdowns: HashMap::new(),
+            requests: 0,
+            bytes: 0,
+            parse_errors: 0,
+            refresh_interval: 0,
+        }
+    }
+}
+
+// Most hits first, ties broken by the key so rows don't jump around between refreshes
+fn sorted_hits<K: Ord>(hits: &HashMap<K, u64>) -> Vec<(&K, &u64)> {
+    let mut hits = hits.iter().collect::<Vec<_>>();
+    hits.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    hits
+}
+
+// Drill-down for the selected row of the focused panel, if it's a section or an address
+fn selected_detail(
+    ui_update: &UIUpdate,
+    layout: &LayoutConfig,
+    navigation: &mut Navigation,
+) -> Option<Detail> {
+    let focus = navigation.focus(&panels(ui_update, layout));
+    let section = |hits: &HashMap<String, u64>| {
+        let index = navigation.selected(focus, hits.len())?;
+        Some(Detail::Section(sorted_hits(hits)[index].0.clone()))
+    };
+    match focus {
+        Panel::Endpoints => section(&ui_update.stats_endpoints),
+        Panel::HttpCode(code) => section(ui_update.stats_http_codes.get(&code)?),
+        Panel::Addresses => {
+            let addresses = &ui_update.stats_addresses;
+            let index = navigation.selected(focus, addresses.len())?;
+            Some(Detail::Address(*sorted_hits(addresses)[index].0))
+        }
+        Panel::History => None,
+    }
+}
+
+// Panels that can take focus, in the order Tab goes through them
+fn panels(ui_update: &UIUpdate, layout: &LayoutConfig) -> Vec<Panel> {
+    let mut codes = ui_update.stats_http_codes.keys().collect::<Vec<_>>();
+    codes.sort();
+    let mut panels = vec![Panel::Endpoints, Panel::Addresses];
+    panels.extend(codes.into_iter().map(|code| Panel::HttpCode(*code)));
+    panels.push(Panel::History);
+    panels.retain(|panel| layout.shows(*panel));
+    panels
+}
+
+// Amount of rows shown in a panel
+fn rows(ui_update: &UIUpdate, alert_history: &VecDeque<AlertEvent>, panel: Panel) -> usize {
+    match panel {
+        Panel::Endpoints => ui_update.stats_endpoints.len(),
+        Panel::Addresses => ui_update.stats_addresses.len(),
+        Panel::HttpCode(code) => ui_update
+            .stats_http_codes
+            .get(&code)
+            .map_or(0, HashMap::len),
+        Panel::History => alert_history.len(),
+    }
+}
+
+// Key bindings shown at the bottom of the screen
+fn footer<'a>(
+    focus: Panel,
+    detail: bool,
+    filter: Option<&'a str>,
+    paused: Option<(DateTime<Local>, usize)>,
+    theme: &Theme,
+) -> Spans<'a> {
+    let key_style = Style::default().add_modifier(Modifier::BOLD);
+    let bindings: &[(&str, &str)] = if detail {
+        &[
+            ("q", "quit"),
+            ("Esc/Enter/Backspace", "close details"),
+            ("p", "pause"),
+            ("\u{2190}/\u{2192}", "step"),
+        ]
+    } else {
+        &[
+            ("q", "quit"),
+            ("?", "help"),
+            ("s", "settings"),
+            ("Tab/Shift-Tab", "switch panel"),
+            ("\u{2191}/\u{2193} j/k", "select"),
+            ("PgUp/PgDn", "page"),
+            ("Home/End", "first/last"),
+            ("Enter", "details"),
+            ("/", "filter"),
+            ("p", "pause"),
+            ("\u{2190}/\u{2192}", "step"),
+        ]
+    };
+    let mut spans = vec![];
+    for (key, action) in bindings.iter() {
+        spans.push(Span::styled(*key, key_style));
+        spans.push(Span::raw(format!(" {}  ", action)));
+    }
+    if !detail {
+        spans.push(Span::styled(
+            format!("Focus: {}", focus.name()),
+            theme.accent,
+        ));
+    }
+    // Everything shown is affected by the filter, it goes first so it's never cut off
+    if let Some(filter) = filter {
+        spans.insert(
+            0,
+            Span::styled(
+                format!("Filter: {}  ", filter),
+                theme.accent.add_modifier(Modifier::BOLD),
+            ),
+        );
+    }
+    // Whatever is shown is not what's happening right now
+    if let Some((time, newer)) = paused {
+        spans.insert(
+            0,
+            Span::styled(
+                format!("PAUSED at {} ({} newer)  ", time.format("%H:%M:%S"), newer),
+                theme
+                    .accent
+                    .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            ),
+        );
+    }
+    Spans::from(spans)
+}
+
+// One line per alert rule, colored by its state
+fn alerts_to_spans<'a>(alerts: &'a [AlertStatus], theme: &Theme) -> Vec<Spans<'a>> {
+    alerts
+        .iter()
+        .map(|alert| {
+            let (state, style) = match (alert.state.firing, alert.state.change_time) {
+                (true, Some(time)) => (
+                    format!("Firing since {}", time.format("%H:%M:%S")),
+                    theme.alert,
+                ),
+                (false, Some(time)) => {
+                    (format!("Resolved at {}", time.format("%H:%M:%S")), theme.ok)
+                }
+                _ => (String::from("Didn't fire since start"), theme.value),
+            };
+            Spans::from(vec![
+                Span::styled(
+                    format!("{}: ", alert.rule.name),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(state, style),
+                Span::raw(format!(" | {:.2}{}", alert.state.value, alert.rule.unit())),
+                // The offending section or IP of grouped rules
+                Span::styled(
+                    match &alert.state.key {
+                        Some(key) => format!(" by {}", key),
+                        None => String::new(),
+                    },
+                    style,
+                ),
+                Span::raw(format!(" ({})", alert.rule)),
+            ])
+        })
+        .collect()
+}
+
+// Area of the given size in the middle of the screen, shrunk if the screen is smaller
+fn popup(area: Rect, width: u16, height: u16) -> Rect {
+    let (width, height) = (width.min(area.width), height.min(area.height));
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+// Some hell of a type, UIs are not easy
+pub fn init_ui(
+) -> Result<Terminal<TermionBackend<AlternateScreen<RawTerminal<std::io::Stdout>>>>, io::Error> {
+    let stdout = io::stdout().into_raw_mode()?;
+    let stdout = AlternateScreen::from(stdout);
+    let backend = TermionBackend::new(stdout);
+    let terminal = Terminal::new(backend)?;
+    Ok(terminal)
+}
+
+// What the UI starts with, the settings can be changed from the UI later on
+pub struct UIOptions {
+    pub refresh_interval: u64,
+    pub http_codes: Vec<u16>,
+    pub filename: String,
+    pub filter_alerts: bool,
+    pub theme: Theme,
+    pub layout: LayoutConfig,
+}
+
+pub fn draw<B>(
+    terminal: &mut Terminal<B>,
+    rx_stats: Receiver<RenderMessage>,
+    tx_control: Sender<Control>,
+    options: UIOptions,
+) -> Result<(), io::Error>
+where
+    B: Backend,
+{
+    let UIOptions {
+        refresh_interval,
+        http_codes,
+        filename,
+        filter_alerts,
+        theme,
+        layout,
+    } = options;
+    let value_style = theme.value;
+    let key_style = theme.key;
+
+    // Content of the UI persists between messages, key presses only change the way it's shown.
+    // The latest updates are kept to look back at them while the display is paused
+    let mut timeline = Timeline::new();
+    // Alert transitions since start, the newest on top
+    let mut alert_history: VecDeque<AlertEvent> = VecDeque::new();
+    let mut navigation = Navigation::default();
+    let mut rate_history = RateHistory::new(refresh_interval);
+    // Drill-down view shown instead of the tables
+    let mut detail: Option<Detail> = None;
+    // Filter expression applied by the stats thread and the one being typed
+    let mut filter: Option<String> = None;
+    let mut filter_prompt: Option<Prompt> = None;
+    // Overlays listing the key bindings and changing the settings at runtime
+    let mut show_help = false;
+    let mut settings = Settings::new(refresh_interval, http_codes);
+    let mut settings_open = false;
+
+    loop {
+        // Wait for something worth re-rendering
+        match rx_stats.recv() {
+            Ok(RenderMessage::UI(update)) => {
+                for event in update.alert_events.iter() {
+                    alert_history.push_front(event.clone());
+                }
+                alert_history.truncate(MAX_ALERT_HISTORY);
+                rate_history.push(&update);
+                settings.observe(&update);
+                timeline.push(*update);
+            }
+            Ok(RenderMessage::Key(key)) => {
+                if let Some(prompt) = filter_prompt.as_mut() {
+                    match prompt.handle(key) {
+                        Outcome::Submit(text) => {
+                            // Submitting an empty filter shows everything again
+                            let parsed = match text.as_str() {
+                                "" => Ok(None),
+                                text => text.parse::<Filter>().map(Some),
+                            };
+                            match parsed {
+                                Ok(parsed) => {
+                                    let _ = tx_control.send(Control::Filter {
+                                        filter: parsed,
+                                        alerts: filter_alerts,
+                                    });
+                                    filter = Some(text).filter(|text| !text.is_empty());
+                                    filter_prompt = None;
+                                }
+                                Err(err) => prompt.error = Some(err),
+                            }
+                        }
+                        Outcome::Cancel => filter_prompt = None,
+                        Outcome::Editing => {}
+                        Outcome::Ignored => continue,
+                    }
+                } else if show_help {
+                    show_help = false;
+                } else if settings_open {
+                    match settings.handle(key) {
+                        Action::Apply(control) => {
+                            if let Control::RefreshInterval(interval) = control {
+                                rate_history.set_refresh_interval(interval);
+                            }
+                            let _ = tx_control.send(control);
+                        }
+                        Action::Close => settings_open = false,
+                        Action::Handled => {}
+                        Action::Ignored => continue,
+                    }
+                } else if key == Key::Char('q') {
+                    break Ok(());
+                } else if key == Key::Char('?') {
+                    show_help = true;
+                } else if key == Key::Char('s') {
+                    settings_open = true;
+                } else if key == Key::Char('/') {
+                    filter_prompt = Some(Prompt::new("Filter", filter.as_deref().unwrap_or("")));
+                } else if timeline.handle(key) {
+                    // Stepping works the same with the details open
+                } else if detail.is_some() {
+                    match key {
+                        Key::Esc | Key::Backspace | Key::Char('\n') => detail = None,
+                        _ => continue,
+                    }
+                } else if key == Key::Char('\n') {
+                    detail = selected_detail(timeline.current(), &layout, &mut navigation);
+                    if detail.is_none() {
+                        continue;
+                    }
+                } else {
+                    let ui_update = timeline.current();
+                    let handled = navigation.handle(key, &panels(ui_update, &layout), |panel| {
+                        rows(ui_update, &alert_history, panel)
+                    });
+                    if !handled {
+                        continue;
+                    }
+                }
+            }
+            Ok(RenderMessage::Fatal(err)) => break Err(io::Error::other(err)),
+            Ok(RenderMessage::Exit) | Err(_) => break Ok(()),
+        }
+        let ui_update = timeline.current();
+        let paused = timeline.paused();
+        let focus = navigation.focus(&panels(ui_update, &layout));
+        terminal.draw(|f| {
+            let mut alert_style = theme.value;
+            // Alerting values and styling
+            if ui_update.alerts.iter().any(|alert| alert.state.firing) {
+                alert_style = theme.alert
+            } else if ui_update
+                .alerts
+                .iter()
+                .any(|alert| alert.state.change_time.is_some())
+            {
+                alert_style = theme.ok
+            }
+            let alerts = &ui_update.alerts;
+
+            // The shown interval may predate a change of the setting, the placeholder shown
+            // before the first one has no length at all
+            let shown_interval = match ui_update.refresh_interval {
+                0 => settings.refresh_interval,
+                refresh_interval => refresh_interval,
+            };
+
+            // General statistics and settings
+            let stats_text = vec![
+                Spans::from(vec![
+                    Span::styled(
+                        format!("Avg hit rate in last {}s: ", shown_interval),
+                        key_style,
+                    ),
+                    Span::styled(format!("{}req/s | ", ui_update.avg_rate), alert_style),
+                    Span::styled("Refresh interval: ", key_style),
+                    Span::styled(format!("{}s | ", settings.refresh_interval), value_style),
+                ]),
+                Spans::from(vec![
+                    Span::styled("Alert rules: ", key_style),
+                    Span::styled(format!("{} | ", alerts.len()), alert_style),
+                    Span::styled("Log file: ", key_style),
+                    Span::styled(format!("{} | ", filename), value_style),
+                ]),
+            ];
+
+            // Sort items so we see the most popular endpoints in the beginning
+            let sorted_endpoints = sorted_hits(&ui_update.stats_endpoints);
+            let sorted_addresses = sorted_hits(&ui_update.stats_addresses);
+
+            // Build table widgets with statistics over endpoints and IP addresses
+            let stats_endpoints: Rows = sorted_endpoints
+                .iter()
+                .map(|(k, v)| {
+                    Row::StyledData(
+                        vec![format!("/{}/*", k), v.to_string()].into_iter(),
+                        value_style,
+                    )
+                })
+                .collect();
+            let stats_addresses: Rows = sorted_addresses
+                .iter()
+                .map(|(k, v)| {
+                    Row::StyledData(vec![k.to_string(), v.to_string()].into_iter(), value_style)
+                })
+                .collect();
+            // Build List widget with log samples
+            let log_samples: Vec<Spans> = ui_update
+                .log_samples
+                .iter()
+                .map(|log| Spans::from(Span::raw(log.as_str())))
+                .collect();
+
+            // Build dynamic blocks of HTTP-codes statistics
+            let mut http_codes_to_stats: HashMap<u16, Rows> = HashMap::new();
+
+            for (http_code, stats) in ui_update.stats_http_codes.iter() {
+                http_codes_to_stats.insert(
+                    *http_code,
+                    sorted_hits(stats)
+                        .iter()
+                        .map(|(k, v)| {
+                            Row::StyledData(
+                                vec![format!("/{}", k), v.to_string()].into_iter(),
+                                value_style,
+                            )
+                        })
+                        .collect(),
+                );
+            }
+            let mut stats_http_codes = http_codes_to_stats.into_iter().collect::<Vec<_>>();
+            stats_http_codes.sort_by_key(|(code, _)| *code);
+
+            let create_block = |title| {
+                Block::default().borders(Borders::ALL).title(Span::styled(
+                    title,
+                    Style::default().add_modifier(Modifier::BOLD),
+                ))
+            };
+            // Focused panel stands out by its border and the highlighted selected row
+            let focus_block = |title: String, panel| {
+                let block = Block::default().borders(Borders::ALL).title(Span::styled(
+                    title,
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+                if panel == focus {
+                    block.border_style(theme.accent)
+                } else {
+                    block
+                }
+            };
+            let highlight_style = |panel| {
+                if panel == focus {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                }
+            };
+
+            let alerts_height = alerts.len() as u16 + 2;
+            let alert = Paragraph::new(alerts_to_spans(alerts, &theme))
+                .block(create_block("Alerts"))
+                .alignment(Alignment::Left)
+                .style(alert_style)
+                .wrap(Wrap { trim: true });
+
+            let stats_general = Paragraph::new(stats_text)
+                .block(create_block("General Stats"))
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+
+            let stats_endpoints = Table::new(
+                vec!["Endpoint", "Hits"].into_iter(),
+                stats_endpoints.into_iter(),
+            )
+            .block(focus_block(
+                String::from("Sorted list of endpoints stats"),
+                Panel::Endpoints,
+            ))
+            .highlight_style(highlight_style(Panel::Endpoints))
+            .widths(&[Constraint::Percentage(80), Constraint::Percentage(20)])
+            .column_spacing(1);
+
+            let stats_addresses = Table::new(
+                vec!["Address", "Hits"].into_iter(),
+                stats_addresses.into_iter(),
+            )
+            .block(focus_block(
+                String::from("Sorted list of addresses stats"),
+                Panel::Addresses,
+            ))
+            .highlight_style(highlight_style(Panel::Addresses))
+            .widths(&[Constraint::Percentage(80), Constraint::Percentage(20)])
+            .column_spacing(1);
+
+            // Dynamically create tables with HTTP-codes statistics
+            let http_codes = stats_http_codes
+                .iter()
+                .map(|(code, stats)| (Panel::HttpCode(*code), stats.len()))
+                .collect::<Vec<_>>();
+            let stats_http_codes: DynTables = stats_http_codes
+                .into_iter()
+                .map(|(code, stats)| {
+                    let panel = Panel::HttpCode(code);
+                    Table::new(
+                        vec![String::from("Endpoint"), String::from("Hits")].into_iter(),
+                        (stats).into_iter(),
+                    )
+                    .block(focus_block(code.to_string(), panel))
+                    .highlight_style(highlight_style(panel))
+                    .widths(&[Constraint::Percentage(80), Constraint::Percentage(20)])
+                    .column_spacing(1)
+                })
+                .collect();
+
+            let log_samples = Paragraph::new(log_samples)
+                .block(create_block("Log samples"))
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: true });
+
+            let history_items = if alert_history.is_empty() {
+                vec![ListItem::new("No alert transitions since start")]
+            } else {
+                alert_history
+                    .iter()
+                    .map(|event| {
+                        let style = if event.firing { theme.alert } else { theme.ok };
+                        ListItem::new(Span::styled(event.to_string(), style))
+                    })
+                    .collect()
+            };
+            let history = List::new(history_items)
+                .block(focus_block(String::from("Alert history"), Panel::History))
+                .highlight_style(highlight_style(Panel::History));
+            let footer = Paragraph::new(match &filter_prompt {
+                Some(prompt) => prompt.spans(&theme),
+                None => footer(focus, detail.is_some(), filter.as_deref(), paused, &theme),
+            });
+            let areas = layout.split(f.size(), alerts_height.max(4));
+            // Dynamically create screen chunks for HTTP-codes statistics
+            let http_codes_chunks = match areas.http_codes {
+                Some(area) if !stats_http_codes.is_empty() => Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![
+                        Constraint::Ratio(1, stats_http_codes.len() as u32);
+                        stats_http_codes.len()
+                    ])
+                    .split(area),
+                _ => vec![],
+            };
+            // Request rate over time with the thresholds of the rules watching it
+            let rates = rate_history.rates();
+            let thresholds = rate_history.thresholds();
+            // The whole block is a bit larger than the plot, the shading is just denser than needed
+            let chart_area = areas.chart.unwrap_or_default();
+            let shading = rate_history.shading(chart_area.width, chart_area.height);
+            let mut datasets = vec![
+                Dataset::default()
+                    .name("firing")
+                    .marker(Marker::Block)
+                    .graph_type(GraphType::Scatter)
+                    .style(theme.shading)
+                    .data(&shading),
+                Dataset::default()
+                    .name("req/s")
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(theme.rate)
+                    .data(&rates),
+            ];
+            // The interval on display while paused
+            let marker = paused.and_then(|(_, newer)| rate_history.marker(newer));
+            if let Some(marker) = marker.as_ref() {
+                datasets.push(
+                    Dataset::default()
+                        .name("paused")
+                        .marker(Marker::Braille)
+                        .graph_type(GraphType::Line)
+                        .style(theme.value)
+                        .data(marker),
+                );
+            }
+            for (name, line) in thresholds.iter() {
+                datasets.push(
+                    Dataset::default()
+                        .name(name.as_str())
+                        .marker(Marker::Braille)
+                        .graph_type(GraphType::Line)
+                        .style(theme.accent)
+                        .data(line),
+                );
+            }
+            let max_rate = rate_history.max();
+            let span = Duration::seconds(rate_history.span() as i64);
+            let rate_chart = Chart::new(datasets)
+                // The legend would cover the latest intervals, the title explains the colors instead
+                .block(Block::default().borders(Borders::ALL).title(vec![
+                    Span::styled(
+                        "Requests per second ",
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled("req/s ", theme.rate),
+                    Span::styled("threshold ", theme.accent),
+                    Span::styled("firing ", theme.alert),
+                    Span::styled(if marker.is_some() { "shown" } else { "" }, theme.value),
+                ]))
+                .hidden_legend_constraints((Constraint::Ratio(0, 1), Constraint::Ratio(0, 1)))
+                .x_axis(
+                    Axis::default()
+                        .bounds([-rate_history.span(), 0.0])
+                        .labels(vec![
+                            Span::raw(format!("-{}", format_duration(span))),
+                            Span::raw(format!("-{}", format_duration(span / 2))),
+                            Span::raw("now"),
+                        ]),
+                )
+                .y_axis(Axis::default().bounds([0.0, max_rate]).labels(vec![
+                    Span::raw("0"),
+                    Span::raw(format!("{:.0}", max_rate / 2.0)),
+                    Span::raw(format!("{:.0}", max_rate)),
+                ]));
+
+            // Render everything!
+            f.render_widget(alert, areas.alerts);
+            if let Some(area) = areas.stats {
+                f.render_widget(stats_general, area);
+            }
+            if let Some(area) = areas.chart {
+                f.render_widget(rate_chart, area);
+            }
+            f.render_widget(footer, areas.footer);
+            if let Some(detail) = &detail {
+                detail::render(f, areas.main, detail, ui_update, &theme);
+            } else {
+                // Rows that fit between the borders and below the table header
+                let page = |area: Rect, header: u16| area.height.saturating_sub(2 + header);
+                if let Some(area) = areas.endpoints {
+                    navigation.set_page(Panel::Endpoints, page(area, 2));
+                    f.render_stateful_widget(
+                        stats_endpoints,
+                        area,
+                        navigation.table_state(Panel::Endpoints, ui_update.stats_endpoints.len()),
+                    );
+                }
+                if let Some(area) = areas.addresses {
+                    navigation.set_page(Panel::Addresses, page(area, 2));
+                    f.render_stateful_widget(
+                        stats_addresses,
+                        area,
+                        navigation.table_state(Panel::Addresses, ui_update.stats_addresses.len()),
+                    );
+                }
+                if let Some(area) = areas.history {
+                    navigation.set_page(Panel::History, page(area, 0));
+                    f.render_stateful_widget(
+                        history,
+                        area,
+                        navigation.history_state(alert_history.len()),
+                    );
+                }
+                if let Some(area) = areas.log_samples {
+                    f.render_widget(log_samples, area);
+                }
+                for ((table, (panel, rows)), area) in stats_http_codes
+                    .into_iter()
+                    .zip(http_codes)
+                    .zip(http_codes_chunks)
+                {
+                    navigation.set_page(panel, page(area, 2));
+                    f.render_stateful_widget(table, area, navigation.table_state(panel, rows));
+                }
+            }
+            // Dialogs go on top of everything else
+            if show_help {
+                help::render(f, f.size(), &theme);
+            } else if settings_open {
+                settings.render(f, f.size(), &theme);
+            }
+        })?;
+    }
+}
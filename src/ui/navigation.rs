@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use termion::event::Key;
+use tui::widgets::{ListState, TableState};
+
+// Rows moved by PgUp/PgDn until the panel was rendered once
+const DEFAULT_PAGE: usize = 10;
+
+// Parts of the UI that can be focused with Tab, in the order they are visited
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Panel {
+    Endpoints,
+    Addresses,
+    HttpCode(u16),
+    History,
+}
+
+impl Panel {
+    pub fn name(self) -> String {
+        match self {
+            Panel::Endpoints => String::from("endpoints"),
+            Panel::Addresses => String::from("addresses"),
+            Panel::HttpCode(code) => format!("{} responses", code),
+            Panel::History => String::from("alert history"),
+        }
+    }
+}
+
+// Focused panel and selected row of every panel, kept between renders
+pub struct Navigation {
+    focus: Panel,
+    selected: HashMap<Panel, usize>,
+    // Rows visible in every panel, as of the last render
+    pages: HashMap<Panel, usize>,
+    // Widget states keep the scroll offset along with the selection
+    tables: HashMap<Panel, TableState>,
+    history: ListState,
+}
+
+impl Default for Navigation {
+    fn default() -> Self {
+        Navigation {
+            focus: Panel::Endpoints,
+            selected: HashMap::new(),
+            pages: HashMap::new(),
+            tables: HashMap::new(),
+            history: ListState::default(),
+        }
+    }
+}
+
+impl Navigation {
+    // Focused panel out of the ones currently shown, the first one if the focused one went away
+    pub fn focus(&mut self, panels: &[Panel]) -> Panel {
+        if !panels.contains(&self.focus) {
+            self.focus = panels.first().copied().unwrap_or(Panel::Endpoints);
+        }
+        self.focus
+    }
+
+    // Move focus or selection, returns false for keys that don't navigate
+    pub fn handle(&mut self, key: Key, panels: &[Panel], rows: impl Fn(Panel) -> usize) -> bool {
+        // The layout might hide every panel
+        if panels.is_empty() {
+            return false;
+        }
+        let focus = self.focus(panels);
+        let index = panels.iter().position(|panel| *panel == focus).unwrap_or(0);
+        let rows = rows(focus);
+        let page = self
+            .pages
+            .get(&focus)
+            .copied()
+            .unwrap_or(DEFAULT_PAGE)
+            .max(1);
+        let selected = self.selected(focus, rows).unwrap_or(0);
+        let selected = match key {
+            Key::Char('\t') => {
+                self.focus = panels[(index + 1) % panels.len()];
+                return true;
+            }
+            Key::BackTab => {
+                self.focus = panels[(index + panels.len() - 1) % panels.len()];
+                return true;
+            }
+            Key::Up | Key::Char('k') => selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => selected + 1,
+            Key::PageUp => selected.saturating_sub(page),
+            Key::PageDown => selected + page,
+            Key::Home => 0,
+            Key::End => rows.saturating_sub(1),
+            _ => return false,
+        };
+        self.selected
+            .insert(focus, selected.min(rows.saturating_sub(1)));
+        true
+    }
+
+    // Selected row of a panel with that many rows, None if it has none
+    pub fn selected(&self, panel: Panel, rows: usize) -> Option<usize> {
+        if rows == 0 {
+            return None;
+        }
+        Some(
+            self.selected
+                .get(&panel)
+                .copied()
+                .unwrap_or(0)
+                .min(rows - 1),
+        )
+    }
+
+    // Remember how many rows of a panel fit on the screen
+    pub fn set_page(&mut self, panel: Panel, rows: u16) {
+        self.pages.insert(panel, rows as usize);
+    }
+
+    pub fn table_state(&mut self, panel: Panel, rows: usize) -> &mut TableState {
+        let selected = self.selected(panel, rows);
+        let state = self.tables.entry(panel).or_default();
+        state.select(selected);
+        state
+    }
+
+    pub fn history_state(&mut self, rows: usize) -> &mut ListState {
+        let selected = self.selected(Panel::History, rows);
+        self.history.select(selected);
+        &mut self.history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PANELS: [Panel; 4] = [
+        Panel::Endpoints,
+        Panel::Addresses,
+        Panel::HttpCode(404),
+        Panel::History,
+    ];
+
+    #[test]
+    fn test_focus() {
+        let mut navigation = Navigation::default();
+        assert!(navigation.handle(Key::Char('\t'), &PANELS, |_| 0));
+        assert!(navigation.handle(Key::Char('\t'), &PANELS, |_| 0));
+        assert_eq!(navigation.focus(&PANELS), Panel::HttpCode(404));
+        assert!(navigation.handle(Key::BackTab, &PANELS, |_| 0));
+        assert_eq!(navigation.focus(&PANELS), Panel::Addresses);
+        navigation.handle(Key::BackTab, &PANELS, |_| 0);
+        navigation.handle(Key::BackTab, &PANELS, |_| 0);
+        assert_eq!(navigation.focus(&PANELS), Panel::History);
+        // Focused table went away with the latest update
+        navigation.handle(Key::BackTab, &PANELS, |_| 0);
+        assert_eq!(
+            navigation.focus(&[Panel::Endpoints, Panel::History]),
+            Panel::Endpoints
+        );
+        assert!(!navigation.handle(Key::Char('x'), &PANELS, |_| 0));
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut navigation = Navigation::default();
+        let rows = |panel| if panel == Panel::Endpoints { 25 } else { 0 };
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(0));
+        assert_eq!(navigation.selected(Panel::Addresses, 0), None);
+        navigation.handle(Key::Down, &PANELS, rows);
+        navigation.handle(Key::Char('j'), &PANELS, rows);
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(2));
+        navigation.handle(Key::PageDown, &PANELS, rows);
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(12));
+        navigation.set_page(Panel::Endpoints, 20);
+        navigation.handle(Key::PageDown, &PANELS, rows);
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(24));
+        navigation.handle(Key::PageUp, &PANELS, rows);
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(4));
+        navigation.handle(Key::Home, &PANELS, rows);
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(0));
+        navigation.handle(Key::Up, &PANELS, rows);
+        assert_eq!(navigation.selected(Panel::Endpoints, 25), Some(0));
+        navigation.handle(Key::End, &PANELS, rows);
+        // Table shrank since
+        assert_eq!(navigation.selected(Panel::Endpoints, 3), Some(2));
+        // Other panels keep their own selection
+        assert_eq!(navigation.selected(Panel::Addresses, 5), Some(0));
+    }
+}
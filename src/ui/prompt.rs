@@ -0,0 +1,93 @@
+use super::Theme;
+use termion::event::Key;
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans},
+};
+
+// What a key press did to the prompt
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Editing,
+    Submit(String),
+    Cancel,
+    // Not a key the prompt knows about
+    Ignored,
+}
+
+// Single line of text typed into the footer, e.g. a filter expression
+pub struct Prompt {
+    label: &'static str,
+    text: String,
+    // Why the last submitted text was rejected
+    pub error: Option<String>,
+}
+
+impl Prompt {
+    pub fn new(label: &'static str, text: &str) -> Self {
+        Prompt {
+            label,
+            text: text.to_owned(),
+            error: None,
+        }
+    }
+
+    pub fn handle(&mut self, key: Key) -> Outcome {
+        match key {
+            Key::Char('\n') => return Outcome::Submit(self.text.trim().to_owned()),
+            Key::Esc => return Outcome::Cancel,
+            Key::Backspace => {
+                self.text.pop();
+            }
+            // Clear the whole line like shells do
+            Key::Ctrl('u') => self.text.clear(),
+            Key::Char(c) if !c.is_control() => self.text.push(c),
+            _ => return Outcome::Ignored,
+        }
+        self.error = None;
+        Outcome::Editing
+    }
+
+    pub fn spans(&self, theme: &Theme) -> Spans<'_> {
+        let mut spans = vec![
+            Span::styled(
+                format!("{}: ", self.label),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(self.text.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ];
+        match &self.error {
+            Some(error) => spans.push(Span::styled(format!("  {}", error), theme.alert)),
+            None => spans.push(Span::raw("  Enter apply  Esc cancel  Ctrl-U clear")),
+        }
+        Spans::from(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle() {
+        let mut prompt = Prompt::new("Filter", "status>=500");
+        prompt.error = Some(String::from("Invalid filter"));
+        assert_eq!(prompt.handle(Key::Backspace), Outcome::Editing);
+        assert_eq!(prompt.error, None);
+        for c in "4 ".chars() {
+            prompt.handle(Key::Char(c));
+        }
+        assert_eq!(prompt.handle(Key::Up), Outcome::Ignored);
+        assert_eq!(
+            prompt.handle(Key::Char('\n')),
+            Outcome::Submit(String::from("status>=504"))
+        );
+        prompt.handle(Key::Ctrl('u'));
+        assert_eq!(
+            prompt.handle(Key::Char('\n')),
+            Outcome::Submit(String::new())
+        );
+        assert_eq!(prompt.handle(Key::Esc), Outcome::Cancel);
+    }
+}
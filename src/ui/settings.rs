@@ -0,0 +1,302 @@
+use super::{
+    popup,
+    prompt::{Outcome, Prompt},
+    Theme, UIUpdate,
+};
+use crate::{Control, MAX_REFRESH_INTERVAL};
+use termion::event::Key;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph, Row, Table, TableState, Wrap},
+    Frame,
+};
+
+// Settings that can be changed without a restart
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    RefreshInterval,
+    AlertThreshold,
+    AlertWindow,
+    HttpCodes,
+}
+
+const FIELDS: [Field; 4] = [
+    Field::RefreshInterval,
+    Field::AlertThreshold,
+    Field::AlertWindow,
+    Field::HttpCodes,
+];
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::RefreshInterval => "Refresh interval",
+            Field::AlertThreshold => "Alert threshold",
+            Field::AlertWindow => "Alert interval",
+            Field::HttpCodes => "HTTP codes",
+        }
+    }
+
+    // Turn the typed text into the change for the stats thread
+    pub fn parse(self, text: &str) -> Result<Control, String> {
+        match self {
+            Field::RefreshInterval => match text.parse::<u64>() {
+                Ok(interval) if (1..=MAX_REFRESH_INTERVAL).contains(&interval) => {
+                    Ok(Control::RefreshInterval(interval))
+                }
+                _ => Err(format!(
+                    "Refresh interval must be between 1 and {} seconds",
+                    MAX_REFRESH_INTERVAL
+                )),
+            },
+            Field::AlertThreshold => match text.parse::<f64>() {
+                Ok(threshold) if threshold.is_finite() && threshold >= 0.0 => {
+                    Ok(Control::AlertThreshold(threshold))
+                }
+                _ => Err(String::from("Threshold must be a positive number of req/s")),
+            },
+            Field::AlertWindow => match text.parse::<u64>() {
+                Ok(window) if window > 0 => Ok(Control::AlertWindow(window)),
+                _ => Err(String::from(
+                    "Alert interval must be a positive number of seconds",
+                )),
+            },
+            Field::HttpCodes => {
+                let mut codes = vec![];
+                for code in text
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|code| !code.is_empty())
+                {
+                    match code.parse::<u16>() {
+                        Ok(code) if (100..600).contains(&code) => codes.push(code),
+                        _ => return Err(format!("Invalid HTTP code '{}'", code)),
+                    }
+                }
+                if codes.is_empty() {
+                    return Err(String::from("At least one HTTP code is needed"));
+                }
+                codes.sort();
+                codes.dedup();
+                Ok(Control::HttpCodes(codes))
+            }
+        }
+    }
+}
+
+// What a key press did to the settings dialog
+#[derive(Debug)]
+pub enum Action {
+    Handled,
+    Apply(Control),
+    Close,
+    Ignored,
+}
+
+// Dialog with the settings the stats are collected with, as far as the UI knows them
+pub struct Settings {
+    pub refresh_interval: u64,
+    http_codes: Vec<u16>,
+    // Of the first rule watching the overall hit rate, None if there is no such rule
+    alert_threshold: Option<f64>,
+    alert_window: Option<u64>,
+    selected: usize,
+    // Value being typed for the selected setting
+    prompt: Option<Prompt>,
+    message: Option<String>,
+}
+
+impl Settings {
+    pub fn new(refresh_interval: u64, http_codes: Vec<u16>) -> Self {
+        Settings {
+            refresh_interval,
+            http_codes,
+            alert_threshold: None,
+            alert_window: None,
+            selected: 0,
+            prompt: None,
+            message: None,
+        }
+    }
+
+    // Alert rules are only known from the stats thread
+    pub fn observe(&mut self, update: &UIUpdate) {
+        let rule = update
+            .alerts
+            .iter()
+            .map(|alert| &alert.rule)
+            .find(|rule| rule.is_total_rate());
+        self.alert_threshold = rule.map(|rule| rule.threshold);
+        self.alert_window = rule.map(|rule| rule.window);
+    }
+
+    fn value(&self, field: Field) -> Option<String> {
+        match field {
+            Field::RefreshInterval => Some(self.refresh_interval.to_string()),
+            Field::AlertThreshold => self.alert_threshold.map(|threshold| threshold.to_string()),
+            Field::AlertWindow => self.alert_window.map(|window| window.to_string()),
+            Field::HttpCodes => Some(
+                self.http_codes
+                    .iter()
+                    .map(u16::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        }
+    }
+
+    // Show the change right away instead of waiting for the next update
+    fn apply(&mut self, control: &Control) {
+        match control {
+            Control::RefreshInterval(interval) => self.refresh_interval = *interval,
+            Control::AlertThreshold(threshold) => self.alert_threshold = Some(*threshold),
+            Control::AlertWindow(window) => self.alert_window = Some(*window),
+            Control::HttpCodes(codes) => self.http_codes = codes.clone(),
+            Control::Filter { .. } => {}
+        }
+    }
+
+    pub fn handle(&mut self, key: Key) -> Action {
+        let field = FIELDS[self.selected];
+        if let Some(prompt) = self.prompt.as_mut() {
+            match prompt.handle(key) {
+                Outcome::Submit(text) => match field.parse(&text) {
+                    Ok(control) => {
+                        self.apply(&control);
+                        self.prompt = None;
+                        self.message = Some(format!("{} changed", field.name()));
+                        return Action::Apply(control);
+                    }
+                    Err(err) => prompt.error = Some(err),
+                },
+                Outcome::Cancel => self.prompt = None,
+                Outcome::Editing => {}
+                Outcome::Ignored => return Action::Ignored,
+            }
+            return Action::Handled;
+        }
+        self.message = None;
+        match key {
+            Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.selected = (self.selected + 1).min(FIELDS.len() - 1),
+            Key::Char('\n') => match self.value(field) {
+                Some(value) => self.prompt = Some(Prompt::new(field.name(), &value)),
+                None => {
+                    self.message = Some(String::from("No alert rule watches the overall hit rate"))
+                }
+            },
+            Key::Esc => return Action::Close,
+            _ => return Action::Ignored,
+        }
+        Action::Handled
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect, theme: &Theme) {
+        let area = popup(area, 80, 12);
+        let block = Block::default().borders(Borders::ALL).title(Span::styled(
+            "Settings (Esc to close)",
+            theme.accent.add_modifier(Modifier::BOLD),
+        ));
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(FIELDS.len() as u16 + 2),
+                    Constraint::Length(1),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(inner);
+
+        let rows = FIELDS.iter().map(|field| {
+            let value = self
+                .value(*field)
+                .unwrap_or_else(|| String::from("no rule"));
+            Row::Data(vec![field.name().to_owned(), value].into_iter())
+        });
+        let table = Table::new(vec!["Setting", "Value"].into_iter(), rows)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&[Constraint::Length(20), Constraint::Min(0)])
+            .column_spacing(1);
+        let mut state = TableState::default();
+        state.select(Some(self.selected));
+        f.render_stateful_widget(table, chunks[0], &mut state);
+
+        let line = match (&self.prompt, &self.message) {
+            (Some(prompt), _) => prompt.spans(theme),
+            (None, Some(message)) => Spans::from(Span::styled(message.as_str(), theme.ok)),
+            (None, None) => Spans::from("Enter edit  \u{2191}/\u{2193} select  Esc close"),
+        };
+        f.render_widget(Paragraph::new(line), chunks[1]);
+        let note = Paragraph::new(
+            "Alert threshold (req/s) and interval (s) apply to every rule watching the overall hit \
+             rate. Changing an interval makes the alerts observe a whole new window before they \
+             change their state.",
+        )
+        .style(Style::default().add_modifier(Modifier::DIM))
+        .wrap(Wrap { trim: true });
+        f.render_widget(note, chunks[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(matches!(
+            Field::RefreshInterval.parse("5"),
+            Ok(Control::RefreshInterval(5))
+        ));
+        assert!(Field::RefreshInterval.parse("0").is_err());
+        assert!(Field::RefreshInterval.parse("300").is_err());
+        assert!(matches!(
+            Field::AlertThreshold.parse("2.5"),
+            Ok(Control::AlertThreshold(threshold)) if threshold == 2.5
+        ));
+        assert!(Field::AlertThreshold.parse("-1").is_err());
+        assert!(Field::AlertWindow.parse("ten").is_err());
+        assert!(matches!(
+            Field::HttpCodes.parse("404, 500,500,"),
+            Ok(Control::HttpCodes(codes)) if codes == vec![404, 500]
+        ));
+        assert_eq!(
+            Field::HttpCodes.parse("404,abc").unwrap_err(),
+            "Invalid HTTP code 'abc'"
+        );
+        assert!(Field::HttpCodes.parse(" ").is_err());
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut settings = Settings::new(10, vec![404]);
+        assert!(matches!(settings.handle(Key::Char('\n')), Action::Handled));
+        for key in [Key::Ctrl('u'), Key::Char('x'), Key::Char('\n')].iter() {
+            settings.handle(*key);
+        }
+        // Still editing after an invalid value
+        assert!(settings.prompt.as_ref().unwrap().error.is_some());
+        settings.handle(Key::Backspace);
+        settings.handle(Key::Char('5'));
+        assert!(matches!(
+            settings.handle(Key::Char('\n')),
+            Action::Apply(Control::RefreshInterval(5))
+        ));
+        assert_eq!(settings.refresh_interval, 5);
+
+        // Nothing to change without a rule watching the overall hit rate
+        settings.handle(Key::Down);
+        settings.handle(Key::Char('\n'));
+        assert!(settings.prompt.is_none());
+        assert!(settings.message.is_some());
+        assert!(matches!(settings.handle(Key::Esc), Action::Close));
+    }
+}
@@ -0,0 +1,282 @@
+use serde::Deserialize;
+use std::{fs, path::Path};
+use tui::style::{Color, Modifier, Style};
+
+// Look of a single kind of text, every field is optional and falls back to the base theme
+//
+// key = { fg = "light-cyan", modifiers = ["bold"] }
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StyleConfig {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+}
+
+// Content of a theme file or of the [theme] section of the config file
+//
+// base = "high-contrast"
+// alert = { fg = "#ff5f5f", modifiers = ["bold"] }
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeConfig {
+    // Built-in theme the styles below are applied on top of
+    pub base: Option<String>,
+    pub key: Option<StyleConfig>,
+    pub value: Option<StyleConfig>,
+    pub accent: Option<StyleConfig>,
+    pub alert: Option<StyleConfig>,
+    pub ok: Option<StyleConfig>,
+    pub rate: Option<StyleConfig>,
+    pub shading: Option<StyleConfig>,
+}
+
+// Styles of everything the UI draws
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    // Labels, e.g. "Refresh interval: "
+    pub key: Style,
+    pub value: Style,
+    // Whatever needs attention without being an alert: focus, filter, thresholds, dialogs
+    pub accent: Style,
+    // Firing alerts and errors
+    pub alert: Style,
+    // Resolved alerts and applied changes
+    pub ok: Style,
+    // Request rate line of the chart
+    pub rate: Style,
+    // Background of the intervals an alert was firing in
+    pub shading: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            key: Style::default().fg(Color::Cyan),
+            value: Style::default().fg(Color::White),
+            accent: Style::default().fg(Color::Yellow),
+            alert: Style::default().fg(Color::Red),
+            ok: Style::default().fg(Color::Green),
+            rate: Style::default().fg(Color::Cyan),
+            shading: Style::default().fg(Color::Red).add_modifier(Modifier::DIM),
+        }
+    }
+}
+
+impl Theme {
+    // Terminal colors only, states are told apart by modifiers
+    pub fn monochrome() -> Self {
+        Theme {
+            key: Style::default(),
+            value: Style::default(),
+            accent: Style::default().add_modifier(Modifier::BOLD),
+            alert: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            ok: Style::default().add_modifier(Modifier::ITALIC),
+            rate: Style::default().add_modifier(Modifier::BOLD),
+            shading: Style::default().add_modifier(Modifier::DIM),
+        }
+    }
+
+    // Bright colors and bold text for bad screens and bad eyes
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Theme {
+            key: bold.fg(Color::LightCyan),
+            value: bold.fg(Color::White),
+            accent: bold.fg(Color::LightYellow),
+            alert: bold.fg(Color::LightRed),
+            ok: bold.fg(Color::LightGreen),
+            rate: bold.fg(Color::LightCyan),
+            shading: Style::default().fg(Color::Red),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::default()),
+            "monochrome" => Some(Theme::monochrome()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn from_config(config: &ThemeConfig) -> Result<Self, String> {
+        let mut theme = match &config.base {
+            Some(base) => {
+                Theme::builtin(base).ok_or_else(|| format!("Unknown base theme '{}'", base))?
+            }
+            None => Theme::default(),
+        };
+        for (style, config) in [
+            (&mut theme.key, &config.key),
+            (&mut theme.value, &config.value),
+            (&mut theme.accent, &config.accent),
+            (&mut theme.alert, &config.alert),
+            (&mut theme.ok, &config.ok),
+            (&mut theme.rate, &config.rate),
+            (&mut theme.shading, &config.shading),
+        ]
+        .iter_mut()
+        {
+            if let Some(config) = config {
+                **style = apply(**style, config)?;
+            }
+        }
+        Ok(theme)
+    }
+
+    // Name of a built-in theme or path to a theme file
+    pub fn load(theme: &str) -> Result<Self, String> {
+        if let Some(theme) = Theme::builtin(theme) {
+            return Ok(theme);
+        }
+        if !Path::new(theme).exists() {
+            return Err(format!(
+                "Theme {} is neither a file nor one of default, monochrome, high-contrast",
+                theme
+            ));
+        }
+        let content = fs::read_to_string(theme)
+            .map_err(|err| format!("Could not read theme file {}: {}", theme, err))?;
+        toml::from_str(&content)
+            .map_err(|err| err.to_string())
+            .and_then(|config| Theme::from_config(&config))
+            .map_err(|err| format!("Invalid theme file {}: {}", theme, err))
+    }
+
+    // The --theme flag wins over the config file, both win over NO_COLOR
+    pub fn resolve(
+        flag: Option<&str>,
+        config: Option<&ThemeConfig>,
+        no_color: bool,
+    ) -> Result<Self, String> {
+        match (flag, config) {
+            (Some(theme), _) => Theme::load(theme),
+            (None, Some(config)) => {
+                Theme::from_config(config).map_err(|err| format!("Invalid theme: {}", err))
+            }
+            (None, None) if no_color => Ok(Theme::monochrome()),
+            (None, None) => Ok(Theme::default()),
+        }
+    }
+}
+
+fn apply(mut style: Style, config: &StyleConfig) -> Result<Style, String> {
+    if let Some(fg) = &config.fg {
+        style.fg = Some(parse_color(fg)?);
+    }
+    if let Some(bg) = &config.bg {
+        style.bg = Some(parse_color(bg)?);
+    }
+    for modifier in config.modifiers.iter() {
+        style = style.add_modifier(parse_modifier(modifier)?);
+    }
+    Ok(style)
+}
+
+// Color name, 256-color palette index or #rrggbb
+fn parse_color(color: &str) -> Result<Color, String> {
+    let named = match color {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" => Color::Gray,
+        "dark-gray" => Color::DarkGray,
+        "light-red" => Color::LightRed,
+        "light-green" => Color::LightGreen,
+        "light-yellow" => Color::LightYellow,
+        "light-blue" => Color::LightBlue,
+        "light-magenta" => Color::LightMagenta,
+        "light-cyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => {
+            if let Ok(index) = color.parse::<u8>() {
+                return Ok(Color::Indexed(index));
+            }
+            let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6);
+            return match hex.and_then(|hex| u32::from_str_radix(hex, 16).ok()) {
+                Some(rgb) => Ok(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)),
+                None => Err(format!("Unknown color '{}'", color)),
+            };
+        }
+    };
+    Ok(named)
+}
+
+fn parse_modifier(modifier: &str) -> Result<Modifier, String> {
+    match modifier {
+        "bold" => Ok(Modifier::BOLD),
+        "dim" => Ok(Modifier::DIM),
+        "italic" => Ok(Modifier::ITALIC),
+        "underlined" => Ok(Modifier::UNDERLINED),
+        "reversed" => Ok(Modifier::REVERSED),
+        _ => Err(format!("Unknown modifier '{}'", modifier)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let config: ThemeConfig = toml::from_str(
+            r##"
+            base = "monochrome"
+            alert = { fg = "light-red", bg = "236", modifiers = ["reversed"] }
+            rate = { fg = "#ff8000" }
+            "##,
+        )
+        .unwrap();
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.key, Theme::monochrome().key);
+        assert_eq!(
+            theme.alert,
+            Style::default()
+                .fg(Color::LightRed)
+                .bg(Color::Indexed(236))
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED | Modifier::REVERSED)
+        );
+        assert_eq!(theme.rate.fg, Some(Color::Rgb(255, 128, 0)));
+
+        let invalid = |content: &str| Theme::from_config(&toml::from_str(content).unwrap());
+        assert_eq!(
+            invalid(r#"base = "solarized""#).unwrap_err(),
+            "Unknown base theme 'solarized'"
+        );
+        assert_eq!(
+            invalid(r##"ok = { fg = "#12345" }"##).unwrap_err(),
+            "Unknown color '#12345'"
+        );
+        assert_eq!(
+            invalid(r#"ok = { modifiers = ["blink"] }"#).unwrap_err(),
+            "Unknown modifier 'blink'"
+        );
+        assert!(toml::from_str::<ThemeConfig>("title = {}").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let config = ThemeConfig {
+            base: Some(String::from("high-contrast")),
+            ..Default::default()
+        };
+        assert_eq!(
+            Theme::resolve(Some("monochrome"), Some(&config), false),
+            Ok(Theme::monochrome())
+        );
+        assert_eq!(
+            Theme::resolve(None, Some(&config), true),
+            Ok(Theme::high_contrast())
+        );
+        assert_eq!(Theme::resolve(None, None, true), Ok(Theme::monochrome()));
+        assert_eq!(Theme::resolve(None, None, false), Ok(Theme::default()));
+        assert!(Theme::resolve(Some("/nonexistent/theme.toml"), None, false).is_err());
+    }
+}
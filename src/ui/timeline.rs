@@ -0,0 +1,126 @@
+use super::UIUpdate;
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use termion::event::Key;
+
+// Amount of past refresh intervals that can be stepped back to
+pub const MAX_SNAPSHOTS: usize = 60;
+
+// The latest UI updates, so the display can be frozen on one of them while new ones keep coming
+pub struct Timeline {
+    // When every update was received, the oldest first
+    snapshots: VecDeque<(DateTime<Local>, UIUpdate)>,
+    // Snapshot shown while paused, counted from the oldest one
+    paused: Option<usize>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        let mut snapshots = VecDeque::new();
+        snapshots.push_back((Local::now(), UIUpdate::default()));
+        Timeline {
+            snapshots,
+            paused: None,
+        }
+    }
+
+    pub fn push(&mut self, update: UIUpdate) {
+        self.snapshots.push_back((Local::now(), update));
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+            // Keep showing the same snapshot, unless it was the one that just went away
+            self.paused = self.paused.map(|index| index.saturating_sub(1));
+        }
+    }
+
+    // Update to display, the latest one unless paused
+    pub fn current(&self) -> &UIUpdate {
+        let index = self.paused.unwrap_or(self.snapshots.len() - 1);
+        &self.snapshots[index].1
+    }
+
+    // Time of the displayed snapshot and how many updates came after it, None if not paused
+    pub fn paused(&self) -> Option<(DateTime<Local>, usize)> {
+        self.paused
+            .map(|index| (self.snapshots[index].0, self.snapshots.len() - 1 - index))
+    }
+
+    // Pause, resume or step, returns false for keys that have nothing to do with the timeline
+    pub fn handle(&mut self, key: Key) -> bool {
+        let latest = self.snapshots.len() - 1;
+        self.paused = match (key, self.paused) {
+            (Key::Char('p'), Some(_)) => None,
+            (Key::Char('p'), None) => Some(latest),
+            // Stepping back pauses on its own
+            (Key::Left, paused) => Some(paused.unwrap_or(latest).saturating_sub(1)),
+            (Key::Right, Some(index)) => Some((index + 1).min(latest)),
+            (Key::Right, None) => None,
+            _ => return false,
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(requests: u64) -> UIUpdate {
+        UIUpdate {
+            requests,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_steps() {
+        let mut timeline = Timeline::new();
+        for requests in 1..=3 {
+            timeline.push(update(requests));
+        }
+        assert_eq!(timeline.current().requests, 3);
+        assert!(timeline.paused().is_none());
+        assert!(!timeline.handle(Key::Up));
+
+        assert!(timeline.handle(Key::Char('p')));
+        timeline.push(update(4));
+        assert_eq!(timeline.current().requests, 3);
+        assert_eq!(timeline.paused().unwrap().1, 1);
+        timeline.handle(Key::Left);
+        timeline.handle(Key::Left);
+        assert_eq!(timeline.current().requests, 1);
+        timeline.handle(Key::Left);
+        timeline.handle(Key::Left);
+        assert_eq!(timeline.current().requests, 0);
+        for _ in 0..5 {
+            timeline.handle(Key::Right);
+        }
+        // Still paused on the latest one
+        assert_eq!(timeline.current().requests, 4);
+        assert_eq!(timeline.paused().unwrap().1, 0);
+        timeline.push(update(5));
+        assert_eq!(timeline.current().requests, 4);
+        timeline.handle(Key::Char('p'));
+        assert_eq!(timeline.current().requests, 5);
+
+        // Stepping back from live
+        timeline.handle(Key::Left);
+        assert_eq!(timeline.current().requests, 4);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut timeline = Timeline::new();
+        timeline.handle(Key::Left);
+        assert_eq!(timeline.paused().unwrap().1, 0);
+        for requests in 1..=MAX_SNAPSHOTS as u64 {
+            timeline.push(update(requests));
+        }
+        // The snapshot that was shown is gone, the oldest one is shown instead
+        assert_eq!(timeline.current().requests, 1);
+        timeline.handle(Key::Right);
+        timeline.push(update(100));
+        assert_eq!(timeline.current().requests, 2);
+        assert_eq!(timeline.paused().unwrap().1, MAX_SNAPSHOTS - 1);
+    }
+}
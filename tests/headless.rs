@@ -0,0 +1,16 @@
+use std::process::Command;
+
+// Errors of worker threads end the process instead of leaving it running without input
+#[test]
+fn test_missing_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_clf-parser"))
+        .args(["--headless", "-f", "/nonexistent/access.log"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Could not read the file /nonexistent/access.log: No such file or directory (os error 2)\n"
+    );
+    assert!(output.stdout.is_empty());
+}